@@ -16,7 +16,8 @@ CREATE TABLE Sites (
 CREATE TABLE Players (
     ID INTEGER PRIMARY KEY,
     Name TEXT UNIQUE,
-    Elo INTEGER
+    Elo INTEGER,
+    NormalizedName TEXT
 );
 
 CREATE TABLE Games (
@@ -39,12 +40,24 @@ CREATE TABLE Games (
     FEN TEXT,
     Moves BLOB,
     PawnHome BLOB,
+    Opening TEXT,
+    MoveText TEXT,
+    Variant TEXT DEFAULT 'Standard',
+    Deleted BOOLEAN NOT NULL DEFAULT 0,
+    Annotator TEXT,
+    Source TEXT,
+    FinalPositionHash INTEGER,
+    IsPuzzle BOOLEAN NOT NULL DEFAULT 0,
+    WhiteRatingDiff INTEGER,
+    BlackRatingDiff INTEGER,
+    FinalMaterial TEXT,
+    Starred BOOLEAN NOT NULL DEFAULT 0,
     FOREIGN KEY(EventID) REFERENCES Events,
     FOREIGN KEY(SiteID) REFERENCES Sites,
     FOREIGN KEY(WhiteID) REFERENCES Players,
     FOREIGN KEY(BlackID) REFERENCES Players
 );
 
-INSERT INTO Players (ID, Name, Elo) VALUES (0, 'Unknown', NULL);
+INSERT INTO Players (ID, Name, Elo, NormalizedName) VALUES (0, 'Unknown', NULL, 'unknown');
 INSERT INTO Events (ID, Name) VALUES (0, 'Unknown');
 INSERT INTO Sites (ID, Name) VALUES (0, 'Unknown');
\ No newline at end of file
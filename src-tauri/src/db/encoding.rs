@@ -15,11 +15,10 @@ pub fn decode_move(byte: u8, chess: &Chess) -> Option<Move> {
 
 pub fn decode_moves(moves_bytes: Vec<u8>, initial_fen: Fen) -> Result<Vec<String>, Error> {
     let mut chess = Chess::from_setup(initial_fen.into(), CastlingMode::Chess960)
-        .or_else(PositionError::ignore_too_much_material)
-        .unwrap();
+        .or_else(PositionError::ignore_too_much_material)?;
     let mut moves = Vec::new();
     for byte in moves_bytes {
-        let m = decode_move(byte, &chess).unwrap();
+        let m = decode_move(byte, &chess).ok_or(Error::InvalidMoveEncoding)?;
         let san = SanPlus::from_move_and_play_unchecked(&mut chess, &m);
         moves.push(san.to_string());
     }
@@ -0,0 +1,6 @@
+CREATE TABLE GameTags (
+    GameID INTEGER NOT NULL,
+    Tag TEXT NOT NULL,
+    PRIMARY KEY (GameID, Tag),
+    FOREIGN KEY(GameID) REFERENCES Games ON DELETE CASCADE
+);
@@ -5,3 +5,6 @@ CREATE INDEX IF NOT EXISTS games_result_idx ON Games(Result);
 CREATE INDEX IF NOT EXISTS games_white_elo_idx ON Games(WhiteElo);
 CREATE INDEX IF NOT EXISTS games_black_elo_idx ON Games(BlackElo);
 CREATE INDEX IF NOT EXISTS games_plycount_idx ON Games(PlyCount);
+CREATE INDEX IF NOT EXISTS games_event_idx ON Games(EventID);
+CREATE INDEX IF NOT EXISTS games_eco_idx ON Games(ECO);
+CREATE INDEX IF NOT EXISTS games_final_position_idx ON Games(FinalPositionHash);
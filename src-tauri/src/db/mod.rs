@@ -1,42 +1,61 @@
 mod encoding;
 mod models;
+mod movetext;
 mod ops;
 mod schema;
 mod search;
+mod source;
+mod speed;
 
 use crate::{
     db::{
         encoding::{decode_move, decode_moves},
         models::*,
+        movetext::MoveTextWriter,
         ops::*,
         schema::*,
     },
     error::Error,
-    opening::get_opening_from_setup,
+    opening::{get_eco_from_setup, get_opening_from_setup},
     AppState,
 };
-use chrono::{NaiveDate, NaiveTime};
+use chrono::{NaiveDate, NaiveTime, Utc};
 use dashmap::DashMap;
 use diesel::{
     connection::{DefaultLoadingMode, SimpleConnection},
+    dsl::{not, sql},
     insert_into,
     prelude::*,
     r2d2::{ConnectionManager, Pool},
     sql_query,
-    sql_types::Text,
+    sql_types::{BigInt, Binary, Bool, Double, Integer, Nullable, Text},
+    sqlite::Sqlite,
 };
-use pgn_reader::{BufferedReader, RawHeader, SanPlus, Skip, Visitor};
+use flate2::{read::MultiGzDecoder, write::GzEncoder, Compression};
+use pgn_reader::{BufferedReader, Nag, RawComment, RawHeader, SanPlus, Skip, Visitor};
 use rayon::prelude::*;
+use rusqlite::types::ValueRef;
 use serde::{Deserialize, Serialize};
 use shakmaty::{
-    fen::Fen, Board, ByColor, Chess, EnPassantMode, FromSetup, Piece, Position, PositionError,
+    fen::Fen,
+    zobrist::{Zobrist64, ZobristHash},
+    Board, ByColor, CastlingMode, Chess, EnPassantMode, FromSetup, Piece, Position, PositionError,
+    Role, Setup,
 };
 use specta::Type;
-use std::io::{BufWriter, Write};
+use std::io::{BufWriter, Read, Write};
 use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
+    ffi::OsStr,
     fs::{remove_file, File, OpenOptions},
-    path::PathBuf,
-    sync::atomic::{AtomicI32, AtomicUsize, Ordering},
+    hash::{Hash, Hasher},
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicUsize, Ordering},
+        mpsc::{sync_channel, Receiver},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 use tauri::State;
@@ -52,14 +71,125 @@ pub use self::models::NormalizedGame;
 pub use self::models::Puzzle;
 pub use self::schema::puzzles;
 pub use self::search::{is_position_in_db, search_position, PositionQuery, PositionStats};
+pub use self::source::Source;
+pub use self::speed::{Speed, SpeedThresholds};
 
-const DATABASE_VERSION: &str = "1.0.0";
+/// Schema version stored in the Info table. Bump it along with
+/// COLUMN_UPGRADES and TABLE_UPGRADES.
+const DATABASE_VERSION: &str = "1.11.0";
 
 const INDEXES_SQL: &str = include_str!("indexes.sql");
 
 const DELETE_INDEXES_SQL: &str = include_str!("delete_indexes.sql");
 
-const CREATE_TABLES_SQL: &str = include_str!("create.sql");
+const GAME_TAGS_SQL: &str = include_str!("game_tags.sql");
+
+/// Creates the tables of a new database, including those of TABLE_UPGRADES.
+const CREATE_TABLES_SQL: &str = concat!(
+    include_str!("create.sql"),
+    "\n",
+    include_str!("game_tags.sql")
+);
+
+/// Tables added after the first release, as (table, statement). The
+/// statements are also part of CREATE_TABLES_SQL.
+const TABLE_UPGRADES: &[(&str, &str)] = &[("GameTags", GAME_TAGS_SQL)];
+
+/// Size of the chunks read ahead of the importer when ImportOptions doesn't set
+/// one.
+const DEFAULT_READ_CHUNK_SIZE: usize = 1 << 20;
+
+/// Largest read chunk size accepted from ImportOptions, as READ_AHEAD_CHUNKS
+/// chunks of it are held in memory at once.
+const MAX_READ_CHUNK_SIZE: usize = 64 << 20;
+
+/// Decompressed bytes of PGN that estimate_import reads and imports to time.
+const ESTIMATE_SAMPLE_SIZE: u64 = 8 << 20;
+
+/// Number of chunks the reading thread may get ahead of the importer, which
+/// bounds the memory used by an import to about this many chunks.
+const READ_AHEAD_CHUNKS: usize = 4;
+
+/// Number of games returned by get_games when the query doesn't set a page size.
+const DEFAULT_GAMES_PAGE_SIZE: i64 = 100;
+
+/// Largest number of games loaded by a single page of get_games,
+/// get_player_games or get_recent_games.
+const MAX_GAMES_PAGE_SIZE: i64 = 1000;
+
+/// Columns added after the first release, as (table, column, statement), where
+/// the statement adds the column to databases created by older versions.
+const COLUMN_UPGRADES: &[(&str, &str, &str)] = &[
+    (
+        "Games",
+        "Opening",
+        "ALTER TABLE Games ADD COLUMN Opening TEXT;",
+    ),
+    (
+        "Games",
+        "MoveText",
+        "ALTER TABLE Games ADD COLUMN MoveText TEXT;",
+    ),
+    (
+        "Games",
+        "Variant",
+        "ALTER TABLE Games ADD COLUMN Variant TEXT DEFAULT 'Standard';",
+    ),
+    (
+        "Players",
+        "NormalizedName",
+        "ALTER TABLE Players ADD COLUMN NormalizedName TEXT;",
+    ),
+    (
+        "Games",
+        "Deleted",
+        "ALTER TABLE Games ADD COLUMN Deleted BOOLEAN NOT NULL DEFAULT 0;",
+    ),
+    (
+        "Games",
+        "Annotator",
+        "ALTER TABLE Games ADD COLUMN Annotator TEXT;",
+    ),
+    (
+        "Games",
+        "Source",
+        "ALTER TABLE Games ADD COLUMN Source TEXT;",
+    ),
+    (
+        "Games",
+        "FinalPositionHash",
+        "ALTER TABLE Games ADD COLUMN FinalPositionHash INTEGER;",
+    ),
+    (
+        "Games",
+        "IsPuzzle",
+        "ALTER TABLE Games ADD COLUMN IsPuzzle BOOLEAN NOT NULL DEFAULT 0;",
+    ),
+    (
+        "Games",
+        "WhiteRatingDiff",
+        "ALTER TABLE Games ADD COLUMN WhiteRatingDiff INTEGER;",
+    ),
+    (
+        "Games",
+        "BlackRatingDiff",
+        "ALTER TABLE Games ADD COLUMN BlackRatingDiff INTEGER;",
+    ),
+    (
+        "Games",
+        "FinalMaterial",
+        "ALTER TABLE Games ADD COLUMN FinalMaterial TEXT;",
+    ),
+    (
+        "Games",
+        "Starred",
+        "ALTER TABLE Games ADD COLUMN Starred BOOLEAN NOT NULL DEFAULT 0;",
+    ),
+];
+
+/// Games from a custom position with at most this many plies are taken to be
+/// puzzles.
+const PUZZLE_MAX_PLIES: usize = 20;
 
 const WHITE_PAWN: Piece = Piece {
     color: shakmaty::Color::White,
@@ -93,48 +223,126 @@ fn get_pawn_home(board: &Board) -> u16 {
     (second_rank_pawns as u16) | ((seventh_rank_pawns as u16) << 8)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum JournalMode {
-    Delete,
+    Wal,
     Off,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
+pub enum Synchronous {
+    Off,
+    Normal,
+}
+
+#[derive(Debug, Clone, Copy)]
 pub struct ConnectionOptions {
     pub journal_mode: JournalMode,
+    pub synchronous: Synchronous,
     pub enable_foreign_keys: bool,
     pub busy_timeout: Option<Duration>,
+    /// Read-only connections leave the journal mode and syncing as they are,
+    /// since changing them needs write access.
+    pub read_only: bool,
 }
 
 impl Default for ConnectionOptions {
     fn default() -> Self {
         Self {
-            journal_mode: JournalMode::Delete,
+            journal_mode: JournalMode::Wal,
+            synchronous: Synchronous::Normal,
             enable_foreign_keys: true,
             busy_timeout: Some(Duration::from_secs(30)),
+            read_only: false,
         }
     }
 }
 
-impl diesel::r2d2::CustomizeConnection<SqliteConnection, diesel::r2d2::Error>
-    for ConnectionOptions
-{
-    fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
-        (|| {
+impl ConnectionOptions {
+    /// Settings for bulk imports. Nothing is journaled or synced, which is
+    /// much faster but can corrupt the database if the import is interrupted
+    /// by a crash or a power loss.
+    pub fn fast_import() -> Self {
+        Self {
+            journal_mode: JournalMode::Off,
+            synchronous: Synchronous::Off,
+            enable_foreign_keys: false,
+            busy_timeout: None,
+            read_only: false,
+        }
+    }
+
+    /// Settings for imports that should survive a crash, at the cost of speed.
+    pub fn safe_import() -> Self {
+        Self {
+            enable_foreign_keys: false,
+            busy_timeout: None,
+            ..Self::default()
+        }
+    }
+
+    /// Settings for connections that only read.
+    pub fn read_only() -> Self {
+        Self {
+            read_only: true,
+            ..Self::default()
+        }
+    }
+
+    fn apply(&self, conn: &mut SqliteConnection) -> QueryResult<()> {
+        if !self.read_only {
             match self.journal_mode {
-                JournalMode::Delete => conn.batch_execute("PRAGMA journal_mode = DELETE;")?,
+                JournalMode::Wal => conn.batch_execute("PRAGMA journal_mode = WAL;")?,
                 JournalMode::Off => conn.batch_execute("PRAGMA journal_mode = OFF;")?,
             }
-            if self.enable_foreign_keys {
-                conn.batch_execute("PRAGMA foreign_keys = ON;")?;
-            }
-            if let Some(d) = self.busy_timeout {
-                conn.batch_execute(&format!("PRAGMA busy_timeout = {};", d.as_millis()))?;
+            match self.synchronous {
+                Synchronous::Off => conn.batch_execute("PRAGMA synchronous = OFF;")?,
+                Synchronous::Normal => conn.batch_execute("PRAGMA synchronous = NORMAL;")?,
             }
-            Ok(())
-        })()
-        .map_err(diesel::r2d2::Error::QueryError)
+        }
+        if self.enable_foreign_keys {
+            conn.batch_execute("PRAGMA foreign_keys = ON;")?;
+        }
+        if let Some(d) = self.busy_timeout {
+            conn.batch_execute(&format!("PRAGMA busy_timeout = {};", d.as_millis()))?;
+        }
+        Ok(())
+    }
+}
+
+impl diesel::r2d2::CustomizeConnection<SqliteConnection, diesel::r2d2::Error>
+    for ConnectionOptions
+{
+    fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+        self.apply(conn).map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+/// Connects to a database with the settings of an import, whether or not it
+/// was already open. [`finish_import`] must be called once the import is done.
+fn get_db_for_import(
+    state: &State<AppState>,
+    db_path: &str,
+    options: ConnectionOptions,
+) -> Result<
+    diesel::r2d2::PooledConnection<diesel::r2d2::ConnectionManager<diesel::SqliteConnection>>,
+    Error,
+> {
+    let mut db = get_db_or_create(state, db_path, options)?;
+    // The pool may have been created earlier with other settings
+    options.apply(&mut db)?;
+    Ok(db)
+}
+
+/// Restores the default settings of a database after an import. The pool
+/// used by the import is closed, so the next commands reconnect with the
+/// default settings too.
+fn finish_import(state: &State<AppState>, db_path: &str) -> Result<(), Error> {
+    if let Some((_, pool)) = state.connection_pool.remove(db_path) {
+        ConnectionOptions::default().apply(&mut pool.get()?)?;
     }
+    close_db(state, db_path);
+    Ok(())
 }
 
 fn get_db_or_create(
@@ -145,23 +353,427 @@ fn get_db_or_create(
     diesel::r2d2::PooledConnection<diesel::r2d2::ConnectionManager<diesel::SqliteConnection>>,
     Error,
 > {
-    let pool = match state.connection_pool.get(db_path) {
-        Some(pool) => pool.clone(),
-        None => {
+    // The entry stays locked while the pool is built, so commands opening the
+    // same database at once don't each create a pool and upgrade the schema
+    let pool = state
+        .connection_pool
+        .entry(db_path.to_string())
+        .or_try_insert_with(|| -> Result<_, Error> {
             let pool = Pool::builder()
                 .max_size(16)
                 .connection_customizer(Box::new(options))
                 .build(ConnectionManager::<SqliteConnection>::new(db_path))?;
-            state
-                .connection_pool
-                .insert(db_path.to_string(), pool.clone());
-            pool
+            migrate(&mut pool.get()?)?;
+            Ok(pool)
+        })?
+        .clone();
+
+    Ok(pool.get()?)
+}
+
+/// Connects to a database for commands that only read from it. The file is
+/// opened read-only, so this works for databases on read-only media and
+/// doesn't take write locks. The schema is still upgraded first when the file
+/// can be written to; a database that can't be upgraded is refused with
+/// [`Error::DatabaseNeedsMigration`].
+fn get_db_read_only(
+    state: &State<AppState>,
+    db_path: &str,
+) -> Result<
+    diesel::r2d2::PooledConnection<diesel::r2d2::ConnectionManager<diesel::SqliteConnection>>,
+    Error,
+> {
+    let uri = read_only_uri(db_path);
+    if !state.connection_pool.contains_key(&uri) {
+        let writable = std::fs::metadata(db_path).is_ok_and(|m| !m.permissions().readonly());
+        if writable {
+            // The permissions don't tell about read-only mounts or locked
+            // files, where the read-only connection still works
+            if let Err(e) = get_db_or_create(state, db_path, ConnectionOptions::default()) {
+                log::warn!("Opening {db_path} read-only: {e}");
+            }
         }
-    };
+    }
+
+    let pool = state
+        .connection_pool
+        .entry(uri.clone())
+        .or_try_insert_with(|| -> Result<_, Error> {
+            let pool = Pool::builder()
+                .max_size(16)
+                .connection_customizer(Box::new(ConnectionOptions::read_only()))
+                .build(ConnectionManager::<SqliteConnection>::new(&uri))?;
+            if needs_migration(&mut pool.get()?)? {
+                return Err(Error::DatabaseNeedsMigration);
+            }
+            Ok(pool)
+        })?
+        .clone();
 
     Ok(pool.get()?)
 }
 
+/// SQLite URI opening the database at `db_path` read-only, also used as the
+/// key of its pool.
+fn read_only_uri(db_path: &str) -> String {
+    let mut path = db_path.replace('\\', "/");
+    // Windows paths start with a drive letter, which needs a leading slash
+    if path.as_bytes().get(1) == Some(&b':') {
+        path.insert(0, '/');
+    }
+    let path = path
+        .replace('%', "%25")
+        .replace('?', "%3f")
+        .replace('#', "%23");
+    format!("file:{path}?mode=ro")
+}
+
+/// Closes the pools of a database, read-write and read-only.
+fn close_db(state: &State<AppState>, db_path: &str) {
+    state.connection_pool.remove(db_path);
+    state.connection_pool.remove(&read_only_uri(db_path));
+}
+
+#[derive(QueryableByName, Debug)]
+struct ColumnInfo {
+    #[diesel(sql_type = Text, column_name = "name")]
+    name: String,
+}
+
+fn table_columns(conn: &mut SqliteConnection, table: &str) -> Result<Vec<String>, Error> {
+    let columns: Vec<ColumnInfo> = sql_query("SELECT name FROM pragma_table_info(?);")
+        .bind::<Text, _>(table)
+        .load(conn)?;
+    Ok(columns.into_iter().map(|c| c.name).collect())
+}
+
+/// Whether a database was created by an older version and misses some of the
+/// tables or columns added since.
+fn needs_migration(conn: &mut SqliteConnection) -> Result<bool, Error> {
+    if table_columns(conn, "Games")?.is_empty() {
+        return Ok(false);
+    }
+    for (table, _) in TABLE_UPGRADES {
+        if table_columns(conn, table)?.is_empty() {
+            return Ok(true);
+        }
+    }
+    for (table, column, _) in COLUMN_UPGRADES {
+        if !table_columns(conn, table)?.iter().any(|c| c == column) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Columns of COLUMN_UPGRADES that are computed from the games already in the
+/// database, with the function filling them in.
+const BACKFILLS: &[(&str, fn(&mut SqliteConnection) -> Result<(), Error>)] = &[
+    ("NormalizedName", fill_normalized_names),
+    ("Source", fill_sources),
+    ("FinalPositionHash", fill_final_position_hashes),
+    ("IsPuzzle", fill_puzzle_flags),
+    ("FinalMaterial", fill_final_materials),
+];
+
+/// Row of the Info table listing the columns added by [`migrate`] that
+/// [`run_backfills`] has yet to fill in, comma-separated.
+const PENDING_BACKFILLS: &str = "PendingBackfills";
+
+/// Whether `version` is newer than DATABASE_VERSION. Versions that can't be
+/// read are taken as older.
+fn is_newer_version(version: &str) -> bool {
+    let parse = |v: &str| {
+        v.split('.')
+            .map(str::parse::<u32>)
+            .collect::<Result<Vec<_>, _>>()
+    };
+    match (parse(version), parse(DATABASE_VERSION)) {
+        (Ok(version), Ok(current)) => version > current,
+        _ => false,
+    }
+}
+
+fn pending_backfills(conn: &mut SqliteConnection) -> Result<Vec<String>, Error> {
+    Ok(get_info(conn, PENDING_BACKFILLS)?
+        .unwrap_or_default()
+        .split(',')
+        .filter(|column| !column.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Brings the schema of a database created by an older version up to
+/// DATABASE_VERSION, adding the tables and columns it's missing. Columns that
+/// have to be computed from the games are left empty and recorded for
+/// [`run_backfills`], so opening a large database stays quick. Does nothing if
+/// the tables haven't been created yet, and refuses databases created by a
+/// newer version.
+fn migrate(conn: &mut SqliteConnection) -> Result<(), Error> {
+    if table_columns(conn, "Games")?.is_empty() {
+        return Ok(());
+    }
+    if get_version(conn)?.is_some_and(|version| is_newer_version(&version)) {
+        return Err(Error::DatabaseTooNew);
+    }
+
+    conn.transaction::<_, Error, _>(|conn| {
+        for (table, sql) in TABLE_UPGRADES {
+            if table_columns(conn, table)?.is_empty() {
+                conn.batch_execute(sql)?;
+            }
+        }
+        let mut pending = pending_backfills(conn)?;
+        let pending_before = pending.len();
+        for (table, column, sql) in COLUMN_UPGRADES {
+            if table_columns(conn, table)?.iter().any(|c| c == column) {
+                continue;
+            }
+            conn.batch_execute(sql)?;
+            if BACKFILLS.iter().any(|(c, _)| c == column) {
+                pending.push(column.to_string());
+            }
+        }
+        if pending.len() != pending_before {
+            set_info(conn, PENDING_BACKFILLS, &pending.join(","))?;
+        }
+        // The version is only bumped once the new columns are filled in
+        if pending.is_empty() && get_version(conn)?.as_deref() != Some(DATABASE_VERSION) {
+            set_info(conn, "Version", DATABASE_VERSION)?;
+        }
+        Ok(())
+    })
+}
+
+/// Fills in the columns recorded by [`migrate`], each in its own transaction,
+/// calling `progress` with the percentage done after each one. A backfill that
+/// is interrupted is run again from the start next time.
+fn run_backfills(conn: &mut SqliteConnection, mut progress: impl FnMut(f64)) -> Result<(), Error> {
+    let mut pending = pending_backfills(conn)?;
+    let total = pending.len();
+    for (column, fill) in BACKFILLS {
+        if !pending.iter().any(|c| c == column) {
+            continue;
+        }
+        conn.transaction::<_, Error, _>(|conn| {
+            fill(conn)?;
+            pending.retain(|c| c != column);
+            set_info(conn, PENDING_BACKFILLS, &pending.join(","))?;
+            if pending.is_empty() {
+                set_info(conn, "Version", DATABASE_VERSION)?;
+            }
+            Ok(())
+        })?;
+        progress((total - pending.len()) as f64 / total as f64 * 100_f64);
+    }
+    Ok(())
+}
+
+fn fill_normalized_names(conn: &mut SqliteConnection) -> Result<(), Error> {
+    let names: Vec<(i32, Option<String>)> = players::table
+        .select((players::id, players::name))
+        .load(conn)?;
+    for (id, name) in names {
+        diesel::update(players::table.filter(players::id.eq(id)))
+            .set(players::normalized_name.eq(name.as_deref().map(normalize_name)))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+fn fill_sources(conn: &mut SqliteConnection) -> Result<(), Error> {
+    let sites: Vec<(i32, Option<String>)> =
+        sites::table.select((sites::id, sites::name)).load(conn)?;
+    for (id, name) in sites {
+        let source = Source::from_site(name.as_deref().unwrap_or_default());
+        diesel::update(games::table.filter(games::site_id.eq(id)))
+            .set(games::source.eq(source.as_str()))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Games replayed at once when filling a column from the final positions, so
+/// that large databases aren't loaded into memory in one go.
+const BACKFILL_CHUNK_SIZE: i64 = 10_000;
+
+/// Replays the games in chunks ordered by id and stores `value` of the final
+/// position of each with `store`. Games that can't be replayed are left as is.
+fn fill_from_final_positions<T: Send>(
+    conn: &mut SqliteConnection,
+    value: impl Fn(&Chess) -> T + Sync,
+    mut store: impl FnMut(&mut SqliteConnection, i32, T) -> QueryResult<usize>,
+) -> Result<(), Error> {
+    let mut last_id = 0;
+    loop {
+        let games: Vec<(i32, Option<String>, Vec<u8>)> = games::table
+            .filter(games::id.gt(last_id))
+            .order(games::id.asc())
+            .limit(BACKFILL_CHUNK_SIZE)
+            .select((games::id, games::fen, games::moves))
+            .load(conn)?;
+        let Some((id, _, _)) = games.last() else {
+            return Ok(());
+        };
+        last_id = *id;
+
+        let values: Vec<(i32, T)> = games
+            .par_iter()
+            .filter_map(|(id, fen, moves)| {
+                let position = replay_game(fen.as_deref(), moves)?;
+                Some((*id, value(&position)))
+            })
+            .collect();
+        for (id, value) in values {
+            store(conn, id, value)?;
+        }
+    }
+}
+
+fn fill_final_position_hashes(conn: &mut SqliteConnection) -> Result<(), Error> {
+    fill_from_final_positions(conn, position_hash, |conn, id, hash| {
+        diesel::update(games::table.filter(games::id.eq(id)))
+            .set(games::final_position_hash.eq(hash))
+            .execute(conn)
+    })
+}
+
+fn fill_final_materials(conn: &mut SqliteConnection) -> Result<(), Error> {
+    fill_from_final_positions(
+        conn,
+        |position| material_signature(position.board()),
+        |conn, id, material| {
+            diesel::update(games::table.filter(games::id.eq(id)))
+                .set(games::final_material.eq(material))
+                .execute(conn)
+        },
+    )
+}
+
+/// Describes the material on a board as the pieces of each side from the king
+/// down, white first, e.g. "KRPvKR".
+fn material_signature(board: &Board) -> String {
+    let side = |color: shakmaty::Color| -> String {
+        [
+            Role::King,
+            Role::Queen,
+            Role::Rook,
+            Role::Bishop,
+            Role::Knight,
+            Role::Pawn,
+        ]
+        .iter()
+        .flat_map(|&role| {
+            let count = (board.by_color(color) & board.by_role(role)).count();
+            std::iter::repeat(role.upper_char()).take(count)
+        })
+        .collect()
+    };
+    format!(
+        "{}v{}",
+        side(shakmaty::Color::White),
+        side(shakmaty::Color::Black)
+    )
+}
+
+/// The same material with the colors swapped, e.g. "KRvKRP" for "KRPvKR".
+fn mirror_material(material: &str) -> String {
+    match material.split_once('v') {
+        Some((white, black)) => format!("{black}v{white}"),
+        None => material.to_string(),
+    }
+}
+
+/// Sets IsPuzzle as [`is_puzzle`] would, with a single statement. Moves are
+/// stored with one byte per ply.
+fn fill_puzzle_flags(conn: &mut SqliteConnection) -> Result<(), Error> {
+    conn.batch_execute(&format!(
+        "UPDATE Games SET IsPuzzle = 1
+        WHERE FEN IS NOT NULL
+            AND (Variant IS NULL OR lower(Variant) IN ('standard', 'from position'))
+            AND length(Moves) <= {PUZZLE_MAX_PLIES};"
+    ))?;
+    Ok(())
+}
+
+/// Whether a game looks like a puzzle: a standard chess game set up from a
+/// custom position, with only the few moves of the solution. A SetUp header
+/// isn't required, as many puzzle collections leave it out, and the stored
+/// FEN already implies it.
+fn is_puzzle(fen: Option<&str>, variant: Option<&str>, ply_count: usize) -> bool {
+    fen.is_some() && is_standard_variant(variant) && ply_count <= PUZZLE_MAX_PLIES
+}
+
+/// Plays the stored moves of a game from its starting position, returning
+/// `None` if the FEN or any move is invalid.
+fn replay_game(fen: Option<&str>, moves: &[u8]) -> Option<Chess> {
+    let mut position: Chess = match fen {
+        Some(fen) => Fen::from_ascii(fen.as_bytes())
+            .ok()?
+            .into_position(CastlingMode::Chess960)
+            .or_else(PositionError::ignore_too_much_material)
+            .ok()?,
+        None => Chess::default(),
+    };
+    for byte in moves {
+        let m = decode_move(*byte, &position)?;
+        position.play_unchecked(&m);
+    }
+    Some(position)
+}
+
+/// Zobrist hash of a position, as stored in the FinalPositionHash column.
+fn position_hash(position: &Chess) -> i64 {
+    let hash: Zobrist64 = position.zobrist_hash(EnPassantMode::Legal);
+    hash.0 as i64
+}
+
+fn get_version(conn: &mut SqliteConnection) -> Result<Option<String>, Error> {
+    get_info(conn, "Version")
+}
+
+fn get_info(conn: &mut SqliteConnection, name: &str) -> Result<Option<String>, Error> {
+    let value = info::table
+        .filter(info::name.eq(name))
+        .select(info::value)
+        .first::<Option<String>>(conn)
+        .optional()?;
+    Ok(value.flatten())
+}
+
+/// Records that the games of a database changed, as an RFC 3339 timestamp in
+/// the UpdatedAt row of the Info table.
+fn touch_database(conn: &mut SqliteConnection) -> Result<(), diesel::result::Error> {
+    set_info(conn, "UpdatedAt", &Utc::now().to_rfc3339())
+}
+
+/// Upgrades a database created by an older version and returns its version.
+/// Opening a database only adds the missing tables and columns; this also
+/// fills in the new columns from the games, emitting DatabaseProgress events
+/// with the path of the file as id.
+#[tauri::command]
+pub async fn migrate_database(
+    file: PathBuf,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<String>, Error> {
+    let db_path = file.to_str().ok_or(Error::InvalidPath)?;
+    let db = &mut get_db_or_create(&state, db_path, ConnectionOptions::default())?;
+    migrate(db)?;
+    let filled = !pending_backfills(db)?.is_empty();
+    run_backfills(db, |progress| {
+        let _ = DatabaseProgress {
+            id: db_path.to_string(),
+            progress,
+        }
+        .emit_all(&app);
+    })?;
+    if filled {
+        clear_search_caches(&state, &file);
+    }
+    get_version(db)
+}
+
 #[derive(Debug)]
 pub struct MaterialColor {
     white: u8,
@@ -195,16 +807,92 @@ pub struct TempGame {
     pub white_elo: Option<i32>,
     pub black_name: Option<String>,
     pub black_elo: Option<i32>,
+    /// Rating gained or lost in the game, from the WhiteRatingDiff and
+    /// BlackRatingDiff headers written by Lichess.
+    pub white_rating_diff: Option<i32>,
+    pub black_rating_diff: Option<i32>,
     pub result: Option<String>,
     pub time_control: Option<String>,
     pub eco: Option<String>,
+    pub opening: Option<String>,
     pub fen: Option<String>,
+    pub variant: Option<String>,
+    pub annotator: Option<String>,
+    pub source: Option<Source>,
+    /// Value of the PlyCount header, checked against the moves read.
+    pub header_ply_count: Option<usize>,
+    /// Number of games read from the file up to and including this one,
+    /// whether they were skipped or not.
+    pub games_read: usize,
     pub moves: Vec<u8>,
+    pub move_text: Option<String>,
     pub position: Chess,
     pub material_count: MaterialColor,
 }
 
+/// Hashes the fields that identify a game for duplicate detection. Missing
+/// names map to "Unknown", the name of the placeholder rows they're stored as.
+fn game_key(
+    white: Option<&str>,
+    black: Option<&str>,
+    date: Option<&str>,
+    site: Option<&str>,
+    moves: &[u8],
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    white.unwrap_or("Unknown").hash(&mut hasher);
+    black.unwrap_or("Unknown").hash(&mut hasher);
+    date.hash(&mut hasher);
+    site.unwrap_or("Unknown").hash(&mut hasher);
+    moves.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn existing_game_keys(db: &mut SqliteConnection) -> Result<HashSet<u64>, Error> {
+    let (white_players, black_players) = diesel::alias!(players as white, players as black);
+    let keys = games::table
+        .inner_join(white_players.on(games::white_id.eq(white_players.field(players::id))))
+        .inner_join(black_players.on(games::black_id.eq(black_players.field(players::id))))
+        .inner_join(sites::table.on(games::site_id.eq(sites::id)))
+        .select((
+            white_players.field(players::name),
+            black_players.field(players::name),
+            games::date,
+            sites::name,
+            games::moves,
+        ))
+        .load_iter::<(
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Vec<u8>,
+        ), DefaultLoadingMode>(db)?
+        .map(|row| {
+            let (white, black, date, site, moves) = row?;
+            Ok(game_key(
+                white.as_deref(),
+                black.as_deref(),
+                date.as_deref(),
+                site.as_deref(),
+                &moves,
+            ))
+        })
+        .collect::<Result<_, diesel::result::Error>>()?;
+    Ok(keys)
+}
+
 impl TempGame {
+    fn key(&self) -> u64 {
+        game_key(
+            self.white_name.as_deref(),
+            self.black_name.as_deref(),
+            self.date.as_deref(),
+            self.site_name.as_deref(),
+            &self.moves,
+        )
+    }
+
     pub fn insert_to_db(&self, db: &mut SqliteConnection) -> Result<(), diesel::result::Error> {
         let pawn_home = get_pawn_home(self.position.board());
 
@@ -235,15 +923,19 @@ impl TempGame {
         let final_material = get_material_count(self.position.board());
         let minimal_white_material = self.material_count.white.min(final_material.white) as i32;
         let minimal_black_material = self.material_count.black.min(final_material.black) as i32;
+        let material = material_signature(self.position.board());
 
         let new_game = NewGame {
             white_id,
             black_id,
             ply_count,
             eco: self.eco.as_deref(),
+            opening: self.opening.as_deref(),
             round: self.round.as_deref(),
             white_elo: self.white_elo,
             black_elo: self.black_elo,
+            white_rating_diff: self.white_rating_diff,
+            black_rating_diff: self.black_rating_diff,
             white_material: minimal_white_material,
             black_material: minimal_black_material,
             // max_rating: self.game.white.rating.max(self.game.black.rating),
@@ -255,6 +947,20 @@ impl TempGame {
             fen: self.fen.as_deref(),
             result: self.result.as_deref(),
             moves: self.moves.as_slice(),
+            move_text: self.move_text.as_deref(),
+            variant: Some(self.variant.as_deref().unwrap_or("Standard")),
+            annotator: self.annotator.as_deref(),
+            source: Some(self.source.unwrap_or(Source::Other).as_str()),
+            // Games with illegal moves are skipped, so the position is the
+            // final one
+            final_position_hash: Some(position_hash(&self.position)),
+            final_material: Some(&material),
+            starred: false,
+            is_puzzle: is_puzzle(
+                self.fen.as_deref(),
+                self.variant.as_deref(),
+                self.moves.len(),
+            ),
             pawn_home: pawn_home as i32,
         };
 
@@ -263,19 +969,228 @@ impl TempGame {
     }
 }
 
+#[derive(Debug, Clone, Default, Deserialize, Type)]
+#[serde(default)]
+pub struct ImportOptions {
+    /// Keep the variations of each game along with its mainline.
+    pub keep_variations: bool,
+    /// Keep the comments and NAGs of each game.
+    pub keep_annotations: bool,
+    /// Skip games where either player has no rating.
+    pub require_ratings: bool,
+    /// Skip games without a result, like ongoing or abandoned games, which are
+    /// otherwise stored with a NULL result.
+    pub skip_unfinished: bool,
+    /// Skip games where either player has the BOT title, as on Lichess.
+    pub skip_bots: bool,
+    /// Skip games that are already in the database, or earlier in the file,
+    /// with the same players, date, site and moves.
+    pub skip_duplicates: bool,
+    /// Number of games inserted per transaction. When unset, the whole file is
+    /// imported in a single transaction.
+    pub batch_size: Option<usize>,
+    /// Size in bytes of the chunks of decompressed PGN handed from the reading
+    /// thread to the importer. Defaults to DEFAULT_READ_CHUNK_SIZE and is
+    /// capped at MAX_READ_CHUNK_SIZE.
+    pub read_chunk_size: Option<usize>,
+    /// Journal and sync the import, so that a crash or a power loss can't
+    /// corrupt the database, at the cost of a slower import. Imports into an
+    /// existing database are always journaled and synced, since they would
+    /// otherwise risk the games it already has.
+    pub safe_import: bool,
+}
+
+impl ImportOptions {
+    fn connection_options(&self, new_database: bool) -> ConnectionOptions {
+        if new_database && !self.safe_import {
+            ConnectionOptions::fast_import()
+        } else {
+            ConnectionOptions::safe_import()
+        }
+    }
+
+    fn keeps_move_text(&self) -> bool {
+        self.keep_variations || self.keep_annotations
+    }
+}
+
+/// Rewrites a PGN date as "YYYY.MM.DD", zero-padding the month and day and
+/// marking unknown components with question marks, so dates sort correctly as
+/// strings. Dates that can't be read are kept as they are.
+fn normalize_date(raw: &str) -> String {
+    fn component(part: &str, width: usize) -> Option<String> {
+        if part.is_empty() || part.chars().all(|c| c == '?') {
+            Some("?".repeat(width))
+        } else if part.len() <= width && part.chars().all(|c| c.is_ascii_digit()) {
+            Some(format!("{:0>width$}", part))
+        } else {
+            None
+        }
+    }
+
+    fn normalize(raw: &str) -> Option<String> {
+        let mut parts = raw.trim().split(['.', '-', '/']);
+        let year = component(parts.next()?, 4)?;
+        let month = component(parts.next()?, 2)?;
+        let day = component(parts.next()?, 2)?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(format!("{}.{}.{}", year, month, day))
+    }
+
+    normalize(raw).unwrap_or_else(|| raw.to_string())
+}
+
+/// Why the importer left out a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SkipReason {
+    /// The game has data that couldn't be parsed.
+    Invalid,
+    /// A move of the mainline is illegal or ambiguous, which usually means the
+    /// PGN is corrupt.
+    IllegalMove,
+    /// A player has the BOT title and `skip_bots` is set.
+    Bot,
+    /// A player has no rating and `require_ratings` is set.
+    Unrated,
+    /// The game was played before the import timestamp.
+    Older,
+    /// The game has no result and `skip_unfinished` is set.
+    Unfinished,
+    /// An interrupted append of the same file already imported the game.
+    Resumed,
+}
+
 struct Importer {
     game: TempGame,
     timestamp: Option<i64>,
-    skip: bool,
+    options: ImportOptions,
+    move_text: MoveTextWriter,
+    /// Why the current game is skipped, if it is.
+    skip: Option<SkipReason>,
+    /// Index in TIME_HEADERS of the header the time of the current game was
+    /// read from.
+    time_header: Option<usize>,
+    /// Games read and skipped so far.
+    stats: ImportStats,
+    /// Number of games at the start of the file that were already read by an
+    /// interrupted append, which are skipped without being looked at.
+    resume_from: usize,
 }
 
+/// Headers the time of a game is read from, by priority. Chess.com exports
+/// also have StartTime and EndTime, which are used for games without UTCTime.
+const TIME_HEADERS: [&[u8]; 3] = [b"UTCTime", b"StartTime", b"EndTime"];
+
 impl Importer {
-    fn new(timestamp: Option<i64>) -> Importer {
+    fn new(timestamp: Option<i64>, options: ImportOptions) -> Importer {
         Importer {
             game: TempGame::default(),
             timestamp,
-            skip: false,
+            options,
+            move_text: MoveTextWriter::default(),
+            skip: None,
+            time_header: None,
+            stats: ImportStats::default(),
+            resume_from: 0,
+        }
+    }
+
+    /// Skips the current game. Resumed games are only counted as such, then
+    /// invalid data and illegal moves take precedence over the other reasons,
+    /// which are counted in the order they are found.
+    fn skip(&mut self, reason: SkipReason) {
+        let is_invalid = |reason| matches!(reason, SkipReason::Invalid | SkipReason::IllegalMove);
+        let replace = match self.skip {
+            None => true,
+            Some(SkipReason::Resumed) => false,
+            Some(current) => is_invalid(reason) && !is_invalid(current),
+        };
+        if replace {
+            self.skip = Some(reason);
+        }
+    }
+
+    fn mark_invalid(&mut self) {
+        self.skip(SkipReason::Invalid);
+    }
+
+    /// Parses a rating header. Unrated players are written as "", "?" or "-",
+    /// and any other value that isn't a number makes the game invalid.
+    fn parse_rating(&mut self, value: &RawHeader<'_>) -> Option<i32> {
+        let value = value.decode_utf8_lossy();
+        let value = value.trim();
+        if matches!(value, "" | "?" | "-") {
+            return None;
+        }
+        let rating = value.parse().ok();
+        if rating.is_none() {
+            self.mark_invalid();
+        }
+        rating
+    }
+
+    /// Sets up the starting position from the FEN header. The standard start
+    /// is only stored for variants with their own rules, like Chess960.
+    fn set_start_position(&mut self) {
+        let Some(raw) = self.game.fen.take() else {
+            return;
+        };
+        let Ok(fen) = Fen::from_ascii(raw.trim().as_bytes()) else {
+            self.mark_invalid();
+            return;
+        };
+
+        let standard = is_standard_variant(self.game.variant.as_deref());
+        if standard && is_standard_start(fen.as_setup()) {
+            return;
         }
+
+        let castling_mode = if standard {
+            CastlingMode::Standard
+        } else {
+            CastlingMode::Chess960
+        };
+        match Chess::from_setup(fen.into_setup(), castling_mode)
+            .or_else(PositionError::ignore_too_much_material)
+        {
+            Ok(position) => {
+                self.game.position = position;
+                self.game.fen = Some(raw.trim().to_string());
+            }
+            Err(_) => self.mark_invalid(),
+        }
+    }
+}
+
+/// Decodes a header holding free text, like a name. Older PGNs are often in
+/// Latin-1 rather than UTF-8, so values that aren't valid UTF-8 are read as
+/// Latin-1, which maps every byte to a character.
+fn decode_text(value: &RawHeader<'_>) -> String {
+    match String::from_utf8(value.decode().into_owned()) {
+        Ok(text) => text,
+        Err(err) => err.as_bytes().iter().map(|&b| char::from(b)).collect(),
+    }
+}
+
+/// Whether a FEN describes the initial position, whatever its move clocks.
+fn is_standard_start(setup: &Setup) -> bool {
+    let mut setup = setup.clone();
+    setup.halfmoves = 0;
+    setup.fullmoves = NonZeroU32::MIN;
+    setup == Setup::default()
+}
+
+/// Whether games of the variant named in a Variant header are played with the
+/// standard rules, possibly from a custom position.
+fn is_standard_variant(variant: Option<&str>) -> bool {
+    match variant {
+        Some(variant) => {
+            variant.eq_ignore_ascii_case("Standard")
+                || variant.eq_ignore_ascii_case("From Position")
+        }
+        None => true,
     }
 }
 
@@ -283,57 +1198,74 @@ impl Visitor for Importer {
     type Result = Option<TempGame>;
 
     fn begin_game(&mut self) {
-        self.skip = false;
+        self.skip = None;
+        self.time_header = None;
+        if self.stats.total_games < self.resume_from {
+            self.skip(SkipReason::Resumed);
+        }
     }
 
     fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
         if key == b"White" {
-            self.game.white_name = Some(value.decode_utf8_lossy().into_owned());
+            self.game.white_name = Some(decode_text(&value));
         } else if key == b"Black" {
-            self.game.black_name = Some(value.decode_utf8_lossy().into_owned());
+            self.game.black_name = Some(decode_text(&value));
         } else if key == b"WhiteElo" {
-            self.game.white_elo = btoi::btoi(value.as_bytes()).ok();
+            self.game.white_elo = self.parse_rating(&value);
         } else if key == b"BlackElo" {
-            self.game.black_elo = btoi::btoi(value.as_bytes()).ok();
+            self.game.black_elo = self.parse_rating(&value);
+        } else if key == b"WhiteRatingDiff" {
+            self.game.white_rating_diff = btoi::btoi(value.as_bytes()).ok();
+        } else if key == b"BlackRatingDiff" {
+            self.game.black_rating_diff = btoi::btoi(value.as_bytes()).ok();
+        } else if key == b"WhiteTitle" || key == b"BlackTitle" {
+            if self.options.skip_bots && value.as_bytes() == b"BOT" {
+                self.skip(SkipReason::Bot);
+            }
         } else if key == b"TimeControl" {
             self.game.time_control = Some(value.decode_utf8_lossy().into_owned());
         } else if key == b"ECO" {
             self.game.eco = Some(value.decode_utf8_lossy().into_owned());
+        } else if key == b"Opening" {
+            self.game.opening = Some(decode_text(&value));
         } else if key == b"Round" {
-            self.game.round = Some(value.decode_utf8_lossy().into_owned());
+            self.game.round = Some(decode_text(&value));
         } else if key == b"Date" || key == b"UTCDate" {
-            self.game.date = Some(String::from_utf8_lossy(value.as_bytes()).to_string());
-        } else if key == b"UTCTime" {
-            self.game.time = Some(String::from_utf8_lossy(value.as_bytes()).to_string());
+            self.game.date = Some(normalize_date(&value.decode_utf8_lossy()));
+        } else if let Some(rank) = TIME_HEADERS.iter().position(|header| *header == key) {
+            if !matches!(self.time_header, Some(current) if current < rank) {
+                self.game.time = Some(value.decode_utf8_lossy().trim().to_string());
+                self.time_header = Some(rank);
+            }
         } else if key == b"Site" {
-            self.game.site_name = Some(String::from_utf8_lossy(value.as_bytes()).to_string());
+            let site = decode_text(&value);
+            self.game.source = Some(Source::from_site(&site));
+            self.game.site_name = Some(site);
         } else if key == b"Event" {
-            self.game.event_name = Some(String::from_utf8_lossy(value.as_bytes()).to_string());
+            self.game.event_name = Some(decode_text(&value));
         } else if key == b"Result" {
-            self.game.result = Some(String::from_utf8_lossy(value.as_bytes()).to_string());
+            // "*" means the game has no result, which is stored as NULL
+            let result = value.decode_utf8_lossy();
+            self.game.result = (result.trim() != "*").then(|| result.trim().to_string());
+        } else if key == b"Annotator" {
+            self.game.annotator = Some(decode_text(&value));
+        } else if key == b"PlyCount" {
+            self.game.header_ply_count = btoi::btoi(value.as_bytes()).ok();
+        } else if key == b"Variant" {
+            self.game.variant = Some(value.decode_utf8_lossy().into_owned());
         } else if key == b"FEN" {
-            if value.as_bytes() == b"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1" {
-                self.game.fen = None;
-            } else {
-                let fen = Fen::from_ascii(value.as_bytes());
-                if let Ok(fen) = fen {
-                    self.game.fen = Some(value.decode_utf8_lossy().into_owned());
-                    if let Ok(setup) =
-                        Chess::from_setup(fen.into_setup(), shakmaty::CastlingMode::Standard)
-                            .or_else(PositionError::ignore_too_much_material)
-                    {
-                        self.game.position = setup;
-                    } else {
-                        self.skip = true;
-                    }
-                } else {
-                    self.skip = true;
-                }
-            }
+            // Parsed in end_headers, once the variant is known
+            self.game.fen = Some(value.decode_utf8_lossy().into_owned());
         }
     }
 
     fn end_headers(&mut self) -> Skip {
+        if self.skip == Some(SkipReason::Resumed) {
+            return Skip(true);
+        }
+
+        self.set_start_position();
+
         // Skip games with timestamp before
         let cur_timestamp = self.game.date.as_ref().and_then(|date| {
             let date = NaiveDate::parse_from_str(date, "%Y.%m.%d").ok()?;
@@ -347,16 +1279,39 @@ impl Visitor for Importer {
 
         if let (Some(cur_timestamp), Some(timestamp)) = (cur_timestamp, self.timestamp) {
             if cur_timestamp <= timestamp {
-                self.skip = true;
+                self.skip(SkipReason::Older);
             }
         }
 
+        if self.options.skip_unfinished && self.game.result.is_none() {
+            self.skip(SkipReason::Unfinished);
+        }
+
         // Skip games without ELO
-        // self.skip |= self.current.white_elo.is_none() || self.current.black_elo.is_none();
-        Skip(self.skip)
-    }
+        if self.options.require_ratings
+            && (self.game.white_elo.is_none() || self.game.black_elo.is_none())
+        {
+            self.skip(SkipReason::Unrated);
+        }
+
+        if self.options.keeps_move_text() {
+            let position = &self.game.position;
+            let ply = (position.fullmoves().get() - 1) * 2
+                + u32::from(position.turn() == shakmaty::Color::Black);
+            self.move_text.start(ply);
+        }
+
+        Skip(self.skip.is_some())
+    }
+
+    fn san(&mut self, san: SanPlus) {
+        if self.options.keeps_move_text() {
+            self.move_text.push_san(&san);
+            if self.move_text.depth() > 0 {
+                return;
+            }
+        }
 
-    fn san(&mut self, san: SanPlus) {
         let m = san.san.to_move(&self.game.position).ok();
         if let Some(m) = m {
             if m.is_promotion() {
@@ -373,24 +1328,199 @@ impl Visitor for Importer {
                 .push(encode_move(&m, &self.game.position).unwrap());
             self.game.position.play_unchecked(&m);
         } else {
-            self.skip = true;
+            self.skip(SkipReason::IllegalMove);
+        }
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        if self.options.keep_annotations {
+            self.move_text.push_nag(nag);
+        }
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        if self.options.keep_annotations {
+            self.move_text
+                .push_comment(&String::from_utf8_lossy(comment.as_bytes()));
         }
     }
 
     fn begin_variation(&mut self) -> Skip {
-        Skip(true) // stay in the mainline
+        if self.options.keep_variations {
+            self.move_text.begin_variation();
+            Skip(false)
+        } else {
+            Skip(true) // stay in the mainline
+        }
+    }
+
+    fn end_variation(&mut self) {
+        if self.options.keep_variations {
+            self.move_text.end_variation();
+        }
     }
 
     fn end_game(&mut self) -> Self::Result {
-        if self.skip {
-            self.game = TempGame::default();
-            None
-        } else {
-            Some(std::mem::take(&mut self.game))
+        self.stats.total_games += 1;
+        self.game.move_text = self.move_text.finish();
+        match self.skip {
+            Some(reason) => {
+                match reason {
+                    SkipReason::Invalid => self.stats.invalid_games += 1,
+                    SkipReason::IllegalMove => self.stats.illegal_move_games += 1,
+                    SkipReason::Bot => self.stats.bot_games += 1,
+                    SkipReason::Unrated => self.stats.unrated_games += 1,
+                    SkipReason::Older => self.stats.older_games += 1,
+                    SkipReason::Unfinished => self.stats.unfinished_games += 1,
+                    SkipReason::Resumed => self.stats.resumed_games += 1,
+                }
+                self.game = TempGame::default();
+                None
+            }
+            None => {
+                // The stored ply count always comes from the moves themselves
+                if let Some(ply_count) = self.game.header_ply_count {
+                    if ply_count != self.game.moves.len() {
+                        log::warn!(
+                            "PlyCount header is {} but {} plies were read",
+                            ply_count,
+                            self.game.moves.len()
+                        );
+                    }
+                }
+                self.game.games_read = self.stats.total_games;
+                Some(std::mem::take(&mut self.game))
+            }
+        }
+    }
+}
+
+/// Wraps a reader and keeps count of the bytes read through it. For compressed
+/// files this counts the compressed bytes, so it can be compared against the
+/// size of the file on disk.
+struct CountingReader<R> {
+    inner: R,
+    bytes_read: Arc<AtomicU64>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes_read.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// Wraps `reader` in the decoder for the compression format matching the file
+/// extension. Files with any other extension are read as plain PGN.
+fn decompress<R: Read + Send + 'static>(
+    reader: R,
+    extension: Option<&OsStr>,
+) -> std::io::Result<Box<dyn Read + Send>> {
+    Ok(match extension.and_then(OsStr::to_str) {
+        Some("bz2") => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+        Some("zst") => Box::new(zstd::Decoder::new(reader)?),
+        Some("gz") => Box::new(MultiGzDecoder::new(reader)),
+        Some("xz") => Box::new(xz2::read::XzDecoder::new_multi_decoder(reader)),
+        _ => Box::new(reader),
+    })
+}
+
+/// Reads a stream on a separate thread, so that reading and decompressing a
+/// file overlap with parsing and inserting its games. The thread stays at most
+/// READ_AHEAD_CHUNKS chunks ahead, and stops once the reader is dropped.
+struct ReadAhead {
+    receiver: Receiver<std::io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl ReadAhead {
+    fn new(mut inner: Box<dyn Read + Send>, chunk_size: usize) -> ReadAhead {
+        let (sender, receiver) = sync_channel(READ_AHEAD_CHUNKS);
+        std::thread::spawn(move || loop {
+            let mut chunk = vec![0; chunk_size];
+            let result = match inner.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => {
+                    chunk.truncate(n);
+                    Ok(chunk)
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => Err(e),
+            };
+            let failed = result.is_err();
+            if sender.send(result).is_err() || failed {
+                break;
+            }
+        });
+        ReadAhead {
+            receiver,
+            chunk: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for ReadAhead {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos == self.chunk.len() {
+            match self.receiver.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk?;
+                    self.pos = 0;
+                }
+                // The reading thread is done
+                Err(_) => return Ok(0),
+            }
         }
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
     }
 }
 
+/// Reads the games of a PGN stream, skipping the ones the importer discards.
+/// Read errors are passed on instead of ending the stream early.
+fn read_games<'a, R: Read + 'a>(
+    reader: R,
+    importer: &'a mut Importer,
+) -> impl Iterator<Item = std::io::Result<TempGame>> + 'a {
+    BufferedReader::new(reader)
+        .into_iter(importer)
+        .filter_map(|game| game.transpose())
+}
+
+#[derive(Debug, Default, Serialize, Type)]
+pub struct ImportStats {
+    /// Games read from the file, whether they were imported or not.
+    pub total_games: usize,
+    /// Games added to the database.
+    pub imported_games: usize,
+    /// Games that were skipped because they couldn't be parsed.
+    pub invalid_games: usize,
+    /// Games that were skipped because a move is illegal in its position.
+    pub illegal_move_games: usize,
+    /// Games that were skipped because they were already imported.
+    pub duplicate_games: usize,
+    /// Games that were skipped because a player is a bot.
+    pub bot_games: usize,
+    /// Games that were skipped because a player has no rating.
+    pub unrated_games: usize,
+    /// Games that were skipped because they were played before the timestamp.
+    pub older_games: usize,
+    /// Games that were skipped because they have no result.
+    pub unfinished_games: usize,
+    /// Games that were skipped because an interrupted append of the same file
+    /// already imported them.
+    pub resumed_games: usize,
+    /// Chess.com monthly archives that couldn't be downloaded, whose games are
+    /// missing from the import.
+    pub failed_archives: usize,
+}
+
+/// Imports the games of a PGN file into the database at `db_path`.
 #[tauri::command]
 #[specta::specta]
 pub async fn convert_pgn(
@@ -400,73 +1530,397 @@ pub async fn convert_pgn(
     app: tauri::AppHandle,
     title: String,
     description: Option<String>,
+    options: Option<ImportOptions>,
     state: tauri::State<'_, AppState>,
-) -> Result<(), Error> {
+) -> Result<ImportStats, Error> {
     let description = description.unwrap_or_default();
-    let extension = file.extension();
+    let options = options.unwrap_or_default();
+    let db_path_str = db_path.to_str().ok_or(Error::InvalidPath)?;
 
     let db_exists = db_path.exists();
 
     // create the database file
-    let db = &mut get_db_or_create(
-        &state,
-        db_path.to_str().unwrap(),
-        ConnectionOptions {
-            enable_foreign_keys: false,
-            busy_timeout: None,
-            journal_mode: JournalMode::Off,
-        },
-    )?;
+    let mut db = get_db_for_import(&state, db_path_str, options.connection_options(!db_exists))?;
 
-    if !db_exists {
-        db.batch_execute(CREATE_TABLES_SQL)?;
-        db.batch_execute(
-            format!(
-                "INSERT INTO Info (Name, Value) VALUES (\"Version\", \"{DATABASE_VERSION}\");
-                INSERT INTO Info (Name, Value) VALUES (\"Title\", \"{title}\");
-                INSERT INTO Info (Name, Value) VALUES (\"Description\", \"{description}\");"
-            )
-            .as_str(),
-        )?;
+    let stats = (|| -> Result<ImportStats, Error> {
+        if !db_exists {
+            let title = if title.trim().is_empty() {
+                default_title(&file)
+            } else {
+                title
+            };
+            create_database(&mut db, &title, &description)?;
+        }
+
+        let stats = import_pgn_file(
+            &mut db,
+            &file,
+            &db_path,
+            timestamp.map(|t| t as i64),
+            options,
+            None,
+            &app,
+            &state,
+        );
+
+        // A cancelled import still keeps the batches committed before it stopped
+        if !db_exists {
+            // Create all the necessary indexes
+            db.batch_execute(INDEXES_SQL)?;
+        }
+        update_info_counts(&mut db)?;
+        stats
+    })();
+
+    // The default settings are restored whether or not the import succeeded
+    drop(db);
+    finish_import(&state, db_path_str)?;
+
+    stats
+}
+
+/// Title of a database imported from `file` without one: the name of the file
+/// up to its first extension, e.g. "games" for "games.pgn.zst".
+fn default_title(file: &Path) -> String {
+    let name = file
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    match name.trim_start_matches('.').split('.').next() {
+        Some(title) if !title.is_empty() => title.to_string(),
+        _ => "Untitled".to_string(),
+    }
+}
+
+/// Name of the Info row holding the checkpoint of an append.
+const IMPORT_CHECKPOINT: &str = "ImportCheckpoint";
+
+/// Batch size of appends without one set, so that they can be resumed without
+/// redoing too much.
+const CHECKPOINT_BATCH_SIZE: usize = 10_000;
+
+/// Progress of an append into a database, saved in the Info table along with
+/// each batch of games.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct ImportCheckpoint {
+    /// Path and size of the PGN file, to tell whether a later append is of the
+    /// same file.
+    file: String,
+    size: u64,
+    /// Games read from the file up to the last committed batch.
+    games: usize,
+}
+
+impl ImportCheckpoint {
+    /// Returns the checkpoint left by an interrupted append of `file` into
+    /// `db`, or a new one if there is none.
+    fn load(db: &mut SqliteConnection, file: &Path) -> Result<ImportCheckpoint, Error> {
+        let new = ImportCheckpoint {
+            file: file.to_string_lossy().into_owned(),
+            size: file.metadata()?.len(),
+            games: 0,
+        };
+        let saved = get_info(db, IMPORT_CHECKPOINT)?
+            .and_then(|value| serde_json::from_str::<ImportCheckpoint>(&value).ok());
+        Ok(match saved {
+            Some(saved) if saved.file == new.file && saved.size == new.size => saved,
+            _ => new,
+        })
+    }
+
+    fn save(&self, db: &mut SqliteConnection) -> Result<(), Error> {
+        set_info(db, IMPORT_CHECKPOINT, &serde_json::to_string(self)?)?;
+        Ok(())
     }
 
-    let file = File::open(&file)?;
+    fn clear(db: &mut SqliteConnection) -> Result<(), Error> {
+        diesel::delete(info::table.filter(info::name.eq(IMPORT_CHECKPOINT))).execute(db)?;
+        Ok(())
+    }
+}
 
-    let uncompressed: Box<dyn std::io::Read + Send> = if extension == Some("bz2".as_ref()) {
-        Box::new(bzip2::read::MultiBzDecoder::new(file))
-    } else if extension == Some("zst".as_ref()) {
-        Box::new(zstd::Decoder::new(file)?)
-    } else {
-        Box::new(file)
+/// Adds the games of a PGN file to an existing database, keeping the games it
+/// already has. Players, events and sites are matched by name.
+///
+/// Progress is saved with every batch of games. If an append stops before the
+/// end, because it was cancelled or the file couldn't be read, appending the
+/// same file again skips the games that were already added.
+#[tauri::command]
+pub async fn append_pgn(
+    db_file: PathBuf,
+    pgn_file: PathBuf,
+    options: Option<ImportOptions>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<ImportStats, Error> {
+    if !db_file.exists() {
+        return Err(Error::DatabaseNotFound);
+    }
+
+    let db_path_str = db_file.to_str().ok_or(Error::InvalidPath)?;
+    let mut options = options.unwrap_or_default();
+    options.batch_size = options.batch_size.or(Some(CHECKPOINT_BATCH_SIZE));
+    let mut db = get_db_for_import(&state, db_path_str, ConnectionOptions::safe_import())?;
+
+    let stats = (|| -> Result<ImportStats, Error> {
+        let checkpoint = ImportCheckpoint::load(&mut db, &pgn_file)?;
+        let stats = import_pgn_file(
+            &mut db,
+            &pgn_file,
+            &db_file,
+            None,
+            options,
+            Some(checkpoint),
+            &app,
+            &state,
+        );
+        if stats.is_ok() {
+            ImportCheckpoint::clear(&mut db)?;
+        }
+        update_info_counts(&mut db)?;
+        stats
+    })();
+
+    // The default settings are restored whether or not the append succeeded
+    drop(db);
+    finish_import(&state, db_path_str)?;
+
+    stats
+}
+
+/// Imports a PGN file, possibly compressed, into `db`, emitting
+/// convert_progress events as it goes.
+fn import_pgn_file(
+    db: &mut SqliteConnection,
+    file: &Path,
+    db_path: &Path,
+    timestamp: Option<i64>,
+    options: ImportOptions,
+    checkpoint: Option<ImportCheckpoint>,
+    app: &tauri::AppHandle,
+    state: &AppState,
+) -> Result<ImportStats, Error> {
+    let extension = file.extension();
+    let file = File::open(file)?;
+    let total_bytes = file.metadata()?.len();
+    let bytes_read = Arc::new(AtomicU64::new(0));
+    let file = CountingReader {
+        inner: file,
+        bytes_read: bytes_read.clone(),
     };
 
+    let chunk_size = options
+        .read_chunk_size
+        .filter(|&size| size > 0)
+        .unwrap_or(DEFAULT_READ_CHUNK_SIZE)
+        .min(MAX_READ_CHUNK_SIZE);
+    let uncompressed = ReadAhead::new(decompress(file, extension)?, chunk_size);
+
     // start counting time
     let start = Instant::now();
 
-    let mut importer = Importer::new(timestamp.map(|t| t as i64));
-    db.transaction::<_, diesel::result::Error, _>(|db| {
-        for (i, game) in BufferedReader::new(uncompressed)
-            .into_iter(&mut importer)
-            .flatten()
-            .flatten()
-            .enumerate()
-        {
-            if i % 1000 == 0 {
-                let elapsed = start.elapsed().as_millis() as u32;
-                app.emit_all("convert_progress", (i, elapsed)).unwrap();
-            }
-            game.insert_to_db(db)?;
+    let cancelled = register_import(state, db_path);
+    let stats = import_games(
+        db,
+        uncompressed,
+        timestamp,
+        options,
+        checkpoint,
+        &cancelled,
+        |i| {
+            let elapsed = start.elapsed().as_millis() as u32;
+            let bytes_read = bytes_read.load(Ordering::Relaxed);
+            let _ = app.emit_all("convert_progress", (i, elapsed, bytes_read, total_bytes));
+        },
+    );
+    unregister_import(state, db_path);
+
+    stats
+}
+
+fn register_import(state: &AppState, db_path: &Path) -> Arc<AtomicBool> {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    state
+        .import_cancellations
+        .insert(db_path.to_string_lossy().into_owned(), cancelled.clone());
+    cancelled
+}
+
+fn unregister_import(state: &AppState, db_path: &Path) {
+    state
+        .import_cancellations
+        .remove(db_path.to_string_lossy().as_ref());
+}
+
+/// Stops the import running into `db_path`, and returns whether there was one.
+/// The games of the batch being imported are rolled back.
+#[tauri::command]
+pub async fn cancel_import(
+    db_path: PathBuf,
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, Error> {
+    match state
+        .import_cancellations
+        .get(db_path.to_string_lossy().as_ref())
+    {
+        Some(cancelled) => {
+            cancelled.store(true, Ordering::Relaxed);
+            Ok(true)
         }
-        Ok(())
-    })?;
+        None => Ok(false),
+    }
+}
 
-    if !db_exists {
-        // Create all the necessary indexes
-        db.batch_execute(INDEXES_SQL)?;
+#[derive(Debug, Serialize)]
+pub struct ImportEstimate {
+    /// Games found in the sampled start of the file.
+    pub sampled_games: usize,
+    pub estimated_games: u64,
+    pub estimated_seconds: u64,
+    /// Whether the whole file fit in the sample, so the game count is exact.
+    pub exact: bool,
+}
+
+/// Estimates how many games a PGN file holds and how long importing it takes.
+/// The first ESTIMATE_SAMPLE_SIZE bytes of decompressed PGN are imported into
+/// an in-memory database, and the game count and time are scaled by the share
+/// of the file on disk the sample took up, which also accounts for the
+/// compression ratio. No database is touched.
+#[tauri::command]
+pub async fn estimate_import(
+    file: PathBuf,
+    options: Option<ImportOptions>,
+) -> Result<ImportEstimate, Error> {
+    let extension = file.extension();
+    let reader = File::open(&file)?;
+    let total_bytes = reader.metadata()?.len();
+    let bytes_read = Arc::new(AtomicU64::new(0));
+    let reader = CountingReader {
+        inner: reader,
+        bytes_read: bytes_read.clone(),
+    };
+
+    let mut sample = Vec::new();
+    decompress(reader, extension)?
+        .take(ESTIMATE_SAMPLE_SIZE + 1)
+        .read_to_end(&mut sample)?;
+    let exact = sample.len() as u64 <= ESTIMATE_SAMPLE_SIZE;
+    sample.truncate(ESTIMATE_SAMPLE_SIZE as usize);
+    let sampled_bytes = bytes_read.load(Ordering::Relaxed).max(1);
+
+    let mut db = SqliteConnection::establish(":memory:")?;
+    db.batch_execute(CREATE_TABLES_SQL)?;
+    let start = Instant::now();
+    let stats = import_games(
+        &mut db,
+        sample.as_slice(),
+        None,
+        options.unwrap_or_default(),
+        None,
+        &AtomicBool::new(false),
+        |_| {},
+    )?;
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let scale = if exact {
+        1.0
+    } else {
+        total_bytes as f64 / sampled_bytes as f64
+    };
+    Ok(ImportEstimate {
+        sampled_games: stats.total_games,
+        estimated_games: (stats.total_games as f64 * scale).round() as u64,
+        estimated_seconds: (elapsed * scale).round() as u64,
+        exact,
+    })
+}
+
+fn create_database(db: &mut SqliteConnection, title: &str, description: &str) -> Result<(), Error> {
+    db.batch_execute(CREATE_TABLES_SQL)?;
+    set_info(db, "Version", DATABASE_VERSION)?;
+    set_info(db, "Title", title)?;
+    set_info(db, "Description", description)?;
+    let now = Utc::now().to_rfc3339();
+    set_info(db, "CreatedAt", &now)?;
+    set_info(db, "UpdatedAt", &now)?;
+    Ok(())
+}
+
+/// Inserts the games read from `reader` into the database. `on_progress` is
+/// called every 1000 games with the number of games read so far.
+///
+/// With a `checkpoint`, the games it counts are skipped as already imported,
+/// and the checkpoint is saved with each batch, so that the import can be
+/// resumed from the last committed batch if it stops. Games are counted as
+/// they appear in the file, before any of them are skipped, so that resuming
+/// with other import options still starts at the right game.
+fn import_games(
+    db: &mut SqliteConnection,
+    reader: impl Read,
+    timestamp: Option<i64>,
+    options: ImportOptions,
+    mut checkpoint: Option<ImportCheckpoint>,
+    cancelled: &AtomicBool,
+    mut on_progress: impl FnMut(usize),
+) -> Result<ImportStats, Error> {
+    let mut seen_games = if options.skip_duplicates {
+        Some(existing_game_keys(db)?)
+    } else {
+        None
+    };
+    let mut duplicate_games = 0;
+    let mut imported_games = 0;
+    // Games read from the file so far, whether imported or not
+    let mut read = 0;
+    let batch_size = options
+        .batch_size
+        .filter(|&size| size > 0)
+        .unwrap_or(usize::MAX);
+    let mut importer = Importer::new(timestamp, options);
+    importer.resume_from = checkpoint.as_ref().map_or(0, |checkpoint| checkpoint.games);
+    let mut games = read_games(reader, &mut importer).enumerate().peekable();
+    while games.peek().is_some() {
+        db.transaction::<_, Error, _>(|db| {
+            for (i, game) in games.by_ref().take(batch_size) {
+                if cancelled.load(Ordering::Relaxed) {
+                    return Err(Error::ImportCancelled);
+                }
+                if i % 1000 == 0 {
+                    on_progress(i);
+                }
+                let game = game?;
+                read = game.games_read;
+                if let Some(seen_games) = seen_games.as_mut() {
+                    if !seen_games.insert(game.key()) {
+                        duplicate_games += 1;
+                        continue;
+                    }
+                }
+                game.insert_to_db(db)?;
+                imported_games += 1;
+            }
+            if let Some(checkpoint) = checkpoint.as_mut() {
+                checkpoint.games = checkpoint.games.max(read);
+                checkpoint.save(db)?;
+            }
+            Ok(())
+        })?;
     }
 
-    // get game, player, event and site counts and to the info table
-    let game_count: i64 = games::table.count().get_result(db)?;
+    Ok(ImportStats {
+        imported_games,
+        duplicate_games,
+        ..importer.stats
+    })
+}
+
+/// Stores the game, player, event and site counts in the Info table, and marks
+/// the database as updated. Games in the trash aren't counted.
+fn update_info_counts(db: &mut SqliteConnection) -> Result<(), Error> {
+    let game_count: i64 = games::table
+        .filter(games::deleted.eq(false))
+        .count()
+        .get_result(db)?;
     let player_count: i64 = players::table.count().get_result(db)?;
     let event_count: i64 = events::table.count().get_result(db)?;
     let site_count: i64 = sites::table.count().get_result(db)?;
@@ -486,110 +1940,515 @@ pub async fn convert_pgn(
             .set(info::value.eq(c.1.to_string()))
             .execute(db)?;
     }
-
+    touch_database(db)?;
     Ok(())
 }
 
-#[derive(Serialize)]
-pub struct DatabaseInfo {
-    title: String,
-    description: String,
-    player_count: usize,
-    event_count: usize,
-    game_count: usize,
-    storage_size: usize,
-    filename: String,
-    indexed: bool,
-}
-
-#[derive(QueryableByName, Debug, Serialize)]
-struct IndexInfo {
-    #[diesel(sql_type = Text, column_name = "name")]
-    _name: String,
-}
-
-fn check_index_exists(conn: &mut SqliteConnection) -> Result<bool, Error> {
-    let query = sql_query("SELECT name FROM pragma_index_list('Games');");
-    let indexes: Vec<IndexInfo> = query.load(conn)?;
-    Ok(!indexes.is_empty())
+/// Lichess and Chess.com usernames only use these characters, which also keeps
+/// them safe to use as a file name and in a URL.
+fn is_valid_username(username: &str) -> bool {
+    !username.is_empty()
+        && username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
 }
 
+/// Imports the games of a Lichess user into a database named after them,
+/// streaming the export from the Lichess API. A token is needed to include
+/// the user's private games.
 #[tauri::command]
-pub async fn get_db_info(
-    file: PathBuf,
+pub async fn import_from_lichess(
+    username: String,
+    token: Option<String>,
+    options: Option<ImportOptions>,
     app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
-) -> Result<DatabaseInfo, Error> {
-    let db_path = PathBuf::from("db").join(file);
+) -> Result<ImportStats, Error> {
+    if !is_valid_username(&username) {
+        return Err(Error::InvalidUsername);
+    }
 
-    let path = resolve_path(
+    let db_path = resolve_path(
         &app.config(),
         app.package_info(),
         &app.env(),
-        db_path,
+        PathBuf::from("db").join(format!("{username}.db3")),
         Some(BaseDirectory::AppData),
     )?;
+    let db_exists = db_path.exists();
+    let options = options.unwrap_or_default();
 
-    let db = &mut get_db_or_create(&state, path.to_str().unwrap(), ConnectionOptions::default())?;
+    let mut db = get_db_for_import(
+        &state,
+        db_path.to_str().unwrap(),
+        options.connection_options(!db_exists),
+    )?;
 
-    let player_count = players::table.count().get_result::<i64>(db)? as usize;
-    let game_count = games::table.count().get_result::<i64>(db)? as usize;
-    let event_count = events::table.count().get_result::<i64>(db)? as usize;
+    if !db_exists {
+        create_database(&mut db, &username, &format!("Lichess games of {username}"))?;
+    }
 
-    let title = match info::table
-        .filter(info::name.eq("Title"))
-        .first(db)
-        .map(|title_info: Info| title_info.value)
-    {
-        Ok(Some(title)) => title,
-        _ => "Untitled".to_string(),
-    };
+    let url = format!("https://lichess.org/api/games/user/{username}");
+    let cancelled = register_import(&state, &db_path);
+    let stats = tokio::task::spawn_blocking(move || -> Result<ImportStats, Error> {
+        let mut request = reqwest::blocking::Client::new()
+            .get(url)
+            .header("Accept", "application/x-chess-pgn");
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send()?;
+        // Lichess asks clients to wait a full minute after a 429
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(Error::RateLimited);
+        }
+        let response = response.error_for_status()?;
 
-    let description = match info::table
-        .filter(info::name.eq("Description"))
-        .first(db)
-        .map(|description_info: Info| description_info.value)
-    {
-        Ok(Some(description)) => description,
-        _ => "".to_string(),
-    };
+        let start = Instant::now();
+        let stats = import_games(&mut db, response, None, options, None, &cancelled, |i| {
+            let elapsed = start.elapsed().as_millis() as u32;
+            let _ = app.emit_all("convert_progress", (i, elapsed, 0, 0));
+        });
 
-    let storage_size = path.metadata()?.len() as usize;
-    let filename = path.file_name().expect("get filename").to_string_lossy();
+        if !db_exists {
+            db.batch_execute(INDEXES_SQL)?;
+        }
+        update_info_counts(&mut db)?;
 
-    let is_indexed = check_index_exists(db)?;
-    Ok(DatabaseInfo {
-        title,
-        description,
-        player_count,
-        game_count,
-        event_count,
-        storage_size,
-        filename: filename.to_string(),
-        indexed: is_indexed,
+        stats
     })
+    .await;
+    unregister_import(&state, &db_path);
+    finish_import(&state, db_path.to_str().unwrap())?;
+
+    stats?
 }
 
-#[tauri::command]
-pub async fn create_indexes(file: PathBuf, state: tauri::State<'_, AppState>) -> Result<(), Error> {
-    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+#[derive(Deserialize)]
+struct ChessComArchives {
+    archives: Vec<String>,
+}
 
-    db.batch_execute(INDEXES_SQL)?;
+#[derive(Deserialize)]
+struct ChessComMonth {
+    games: Vec<ChessComGame>,
+}
 
-    Ok(())
+#[derive(Deserialize)]
+struct ChessComGame {
+    /// Missing for some games, like daily games that are still in progress.
+    pgn: Option<String>,
 }
 
-#[tauri::command]
-pub async fn delete_indexes(file: PathBuf, state: tauri::State<'_, AppState>) -> Result<(), Error> {
-    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+/// Reads the PGN of the games in a list of Chess.com monthly archives, only
+/// downloading each month once the previous one has been read. Months that
+/// fail to download are logged, counted in `failed_archives` and skipped.
+struct ChessComReader {
+    client: reqwest::blocking::Client,
+    archives: std::vec::IntoIter<String>,
+    month: std::io::Cursor<Vec<u8>>,
+    failed_archives: Arc<AtomicUsize>,
+}
 
-    db.batch_execute(DELETE_INDEXES_SQL)?;
+impl ChessComReader {
+    fn fetch_month(&self, url: &str) -> Result<Vec<u8>, Error> {
+        let month: ChessComMonth = self.client.get(url).send()?.error_for_status()?.json()?;
+        let mut pgn = Vec::new();
+        for game in month.games.into_iter().filter_map(|game| game.pgn) {
+            pgn.extend_from_slice(game.as_bytes());
+            pgn.extend_from_slice(b"\n\n");
+        }
+        Ok(pgn)
+    }
+}
 
-    Ok(())
+impl Read for ChessComReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            let read = self.month.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            let Some(url) = self.archives.next() else {
+                return Ok(0);
+            };
+            match self.fetch_month(&url) {
+                Ok(pgn) => self.month = std::io::Cursor::new(pgn),
+                Err(e) => {
+                    log::warn!("Skipping Chess.com archive {url}: {e}");
+                    self.failed_archives.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
 }
 
+/// Imports the games of a Chess.com user from their monthly archives on the
+/// public API. They go in their own database, named after the user, so they
+/// aren't mixed with games imported from Lichess.
 #[tauri::command]
-pub async fn edit_db_info(
+pub async fn import_from_chesscom(
+    username: String,
+    options: Option<ImportOptions>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<ImportStats, Error> {
+    if !is_valid_username(&username) {
+        return Err(Error::InvalidUsername);
+    }
+
+    let db_path = resolve_path(
+        &app.config(),
+        app.package_info(),
+        &app.env(),
+        PathBuf::from("db").join(format!("{username}-chesscom.db3")),
+        Some(BaseDirectory::AppData),
+    )?;
+    let db_exists = db_path.exists();
+    let options = options.unwrap_or_default();
+
+    let mut db = get_db_for_import(
+        &state,
+        db_path.to_str().unwrap(),
+        options.connection_options(!db_exists),
+    )?;
+
+    if !db_exists {
+        create_database(
+            &mut db,
+            &username,
+            &format!("Chess.com games of {username}"),
+        )?;
+    }
+
+    let url = format!("https://api.chess.com/pub/player/{username}/games/archives");
+    let cancelled = register_import(&state, &db_path);
+    let stats = tokio::task::spawn_blocking(move || -> Result<ImportStats, Error> {
+        // The Chess.com API rejects requests without a user agent
+        let client = reqwest::blocking::Client::builder()
+            .user_agent("en-croissant")
+            .build()?;
+        let response = client.get(url).send()?;
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(Error::RateLimited);
+        }
+        let archives: ChessComArchives = response.error_for_status()?.json()?;
+        let failed_archives = Arc::new(AtomicUsize::new(0));
+        let reader = ChessComReader {
+            client,
+            archives: archives.archives.into_iter(),
+            month: std::io::Cursor::new(Vec::new()),
+            failed_archives: failed_archives.clone(),
+        };
+
+        let start = Instant::now();
+        let stats = import_games(&mut db, reader, None, options, None, &cancelled, |i| {
+            let elapsed = start.elapsed().as_millis() as u32;
+            let _ = app.emit_all("convert_progress", (i, elapsed, 0, 0));
+        });
+
+        if !db_exists {
+            db.batch_execute(INDEXES_SQL)?;
+        }
+        update_info_counts(&mut db)?;
+
+        stats.map(|stats| ImportStats {
+            failed_archives: failed_archives.load(Ordering::Relaxed),
+            ..stats
+        })
+    })
+    .await;
+    unregister_import(&state, &db_path);
+    finish_import(&state, db_path.to_str().unwrap())?;
+
+    stats?
+}
+
+#[derive(Serialize)]
+pub struct DatabaseInfo {
+    title: String,
+    description: String,
+    /// Schema version, missing for databases created before it was recorded.
+    version: Option<String>,
+    /// RFC 3339 timestamps of when the database was created and when its games
+    /// last changed, missing for databases created before they were recorded.
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    player_count: usize,
+    event_count: usize,
+    game_count: usize,
+    storage_size: usize,
+    /// Bytes used by each part of the database, when SQLite was built with the
+    /// dbstat virtual table.
+    games_size: Option<usize>,
+    players_size: Option<usize>,
+    index_size: Option<usize>,
+    /// Mean number of plies per game, missing for an empty database.
+    average_plies: Option<f64>,
+    /// Share of games with each result, as percentages.
+    white_win_pct: Option<f64>,
+    black_win_pct: Option<f64>,
+    draw_pct: Option<f64>,
+    filename: String,
+    indexed: bool,
+}
+
+#[derive(QueryableByName, Debug)]
+struct GameAggregates {
+    #[diesel(sql_type = Nullable<Double>)]
+    average_plies: Option<f64>,
+    #[diesel(sql_type = Nullable<Double>)]
+    white_win_pct: Option<f64>,
+    #[diesel(sql_type = Nullable<Double>)]
+    black_win_pct: Option<f64>,
+    #[diesel(sql_type = Nullable<Double>)]
+    draw_pct: Option<f64>,
+}
+
+/// Averages over the games that aren't in the trash, rounded to one decimal.
+/// All of them are NULL when there are no games.
+fn get_game_aggregates(conn: &mut SqliteConnection) -> Result<GameAggregates, Error> {
+    let aggregates = sql_query(
+        "SELECT ROUND(AVG(PlyCount), 1) AS average_plies,
+            ROUND(AVG(Result IS '1-0') * 100, 1) AS white_win_pct,
+            ROUND(AVG(Result IS '0-1') * 100, 1) AS black_win_pct,
+            ROUND(AVG(Result IS '1/2-1/2') * 100, 1) AS draw_pct
+        FROM Games WHERE NOT Deleted;",
+    )
+    .get_result(conn)?;
+    Ok(aggregates)
+}
+
+#[derive(QueryableByName, Debug)]
+struct StorageInfo {
+    #[diesel(sql_type = Text)]
+    name: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    kind: Option<String>,
+    #[diesel(sql_type = BigInt)]
+    size: i64,
+}
+
+#[derive(Debug, Default)]
+struct StorageSizes {
+    games: usize,
+    players: usize,
+    indexes: usize,
+}
+
+fn get_storage_sizes(conn: &mut SqliteConnection) -> Result<StorageSizes, Error> {
+    let query = sql_query(
+        "SELECT s.name AS name, m.type AS kind, SUM(s.pgsize) AS size
+        FROM dbstat s LEFT JOIN sqlite_master m ON m.name = s.name
+        GROUP BY s.name;",
+    );
+    let rows: Vec<StorageInfo> = query.load(conn)?;
+
+    let mut sizes = StorageSizes::default();
+    for row in rows {
+        let size = row.size as usize;
+        if row.kind.as_deref() == Some("index") {
+            sizes.indexes += size;
+        } else if row.name == "Games" {
+            sizes.games += size;
+        } else if row.name == "Players" {
+            sizes.players += size;
+        }
+    }
+    Ok(sizes)
+}
+
+#[derive(QueryableByName, Debug, Serialize)]
+struct IndexInfo {
+    #[diesel(sql_type = Text, column_name = "name")]
+    _name: String,
+}
+
+fn check_index_exists(conn: &mut SqliteConnection) -> Result<bool, Error> {
+    let query = sql_query("SELECT name FROM pragma_index_list('Games');");
+    let indexes: Vec<IndexInfo> = query.load(conn)?;
+    Ok(!indexes.is_empty())
+}
+
+#[tauri::command]
+pub async fn get_db_info(
+    file: PathBuf,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<DatabaseInfo, Error> {
+    let db_path = PathBuf::from("db").join(file);
+
+    let path = resolve_path(
+        &app.config(),
+        app.package_info(),
+        &app.env(),
+        db_path,
+        Some(BaseDirectory::AppData),
+    )?;
+
+    database_info(&state, &path)
+}
+
+fn database_info(state: &State<AppState>, path: &Path) -> Result<DatabaseInfo, Error> {
+    let db = &mut get_db_read_only(state, path.to_str().unwrap())?;
+
+    let player_count = players::table.count().get_result::<i64>(db)? as usize;
+    // Games in the trash are left out, as in the aggregates
+    let game_count = games::table
+        .filter(games::deleted.eq(false))
+        .count()
+        .get_result::<i64>(db)? as usize;
+    let event_count = events::table.count().get_result::<i64>(db)? as usize;
+
+    let title = match info::table
+        .filter(info::name.eq("Title"))
+        .first(db)
+        .map(|title_info: Info| title_info.value)
+    {
+        Ok(Some(title)) => title,
+        _ => "Untitled".to_string(),
+    };
+
+    let description = match info::table
+        .filter(info::name.eq("Description"))
+        .first(db)
+        .map(|description_info: Info| description_info.value)
+    {
+        Ok(Some(description)) => description,
+        _ => "".to_string(),
+    };
+
+    let version = get_version(db)?;
+    let created_at = get_info(db, "CreatedAt")?;
+    let updated_at = get_info(db, "UpdatedAt")?;
+
+    let storage_size = path.metadata()?.len() as usize;
+    let filename = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+
+    // dbstat is an optional SQLite feature, so only the total is known without it
+    let sizes = get_storage_sizes(db).ok();
+
+    let aggregates = get_game_aggregates(db)?;
+
+    let is_indexed = check_index_exists(db)?;
+    Ok(DatabaseInfo {
+        title,
+        description,
+        version,
+        created_at,
+        updated_at,
+        player_count,
+        game_count,
+        event_count,
+        storage_size,
+        games_size: sizes.as_ref().map(|s| s.games),
+        players_size: sizes.as_ref().map(|s| s.players),
+        index_size: sizes.as_ref().map(|s| s.indexes),
+        average_plies: aggregates.average_plies,
+        white_win_pct: aggregates.white_win_pct,
+        black_win_pct: aggregates.black_win_pct,
+        draw_pct: aggregates.draw_pct,
+        filename: filename.to_string(),
+        indexed: is_indexed,
+    })
+}
+
+/// Whether a file starts with the header of an SQLite database. Checked before
+/// connecting, since the pool keeps retrying connections that fail.
+fn is_sqlite_file(path: &Path) -> bool {
+    let mut header = [0; 16];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok()
+        && &header == b"SQLite format 3\0"
+}
+
+/// Returns the info of every database in the AppData "db" directory, sorted by
+/// file name. Files that can't be read as a database are skipped, and a missing
+/// directory means there are no databases.
+#[tauri::command]
+pub async fn list_databases(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<DatabaseInfo>, Error> {
+    let dir = resolve_path(
+        &app.config(),
+        app.package_info(),
+        &app.env(),
+        "db",
+        Some(BaseDirectory::AppData),
+    )?;
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            matches!(
+                path.extension().and_then(OsStr::to_str),
+                Some("db3" | "sqlite")
+            ) && is_sqlite_file(path)
+        })
+        .collect();
+    paths.sort();
+
+    Ok(paths
+        .iter()
+        .filter_map(|path| match database_info(&state, path) {
+            Ok(info) => Some(info),
+            Err(e) => {
+                log::warn!("Skipping {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// Recomputes the ply count of the games whose count is missing or doesn't
+/// match their moves, and returns the number of games fixed.
+#[tauri::command]
+pub async fn recompute_ply_counts(
+    file: PathBuf,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+
+    // Moves are stored one byte per ply
+    let updated =
+        sql_query("UPDATE Games SET PlyCount = LENGTH(Moves) WHERE PlyCount IS NOT LENGTH(Moves);")
+            .execute(db)?;
+    if updated > 0 {
+        touch_database(db)?;
+    }
+    Ok(updated)
+}
+
+#[tauri::command]
+pub async fn create_indexes(file: PathBuf, state: tauri::State<'_, AppState>) -> Result<(), Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+
+    db.batch_execute(INDEXES_SQL)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_indexes(file: PathBuf, state: tauri::State<'_, AppState>) -> Result<(), Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+
+    db.batch_execute(DELETE_INDEXES_SQL)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn edit_db_info(
     file: PathBuf,
     title: Option<String>,
     description: Option<String>,
@@ -598,29 +2457,31 @@ pub async fn edit_db_info(
     let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
 
     if let Some(title) = title {
-        diesel::insert_into(info::table)
-            .values((info::name.eq("Title"), info::value.eq(title.clone())))
-            .on_conflict(info::name)
-            .do_update()
-            .set(info::value.eq(title))
-            .execute(db)?;
+        set_info(db, "Title", &title)?;
     }
 
     if let Some(description) = description {
-        diesel::insert_into(info::table)
-            .values((
-                info::name.eq("Description"),
-                info::value.eq(description.clone()),
-            ))
-            .on_conflict(info::name)
-            .do_update()
-            .set(info::value.eq(description))
-            .execute(db)?;
+        set_info(db, "Description", &description)?;
     }
 
     Ok(())
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum PlayerResult {
+    Win,
+    Loss,
+    Draw,
+}
+
+/// Matches the games with the given result for a player, whichever color
+/// they played.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct PlayerResultQuery {
+    pub player: i32,
+    pub result: PlayerResult,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Sides {
     BlackWhite,
@@ -628,11 +2489,21 @@ pub enum Sides {
     Any,
 }
 
+/// Which players of a game must be within the rating bounds of a [`GameQuery`].
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum RatingMatch {
+    #[default]
+    Both,
+    Either,
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum GameSort {
     #[default]
     #[serde(rename = "id")]
     Id,
+    /// Orders by date, then by time of day within a date. Games without a time
+    /// come first in ascending order, and ties are kept in id order.
     #[serde(rename = "date")]
     Date,
     #[serde(rename = "whiteElo")]
@@ -641,6 +2512,18 @@ pub enum GameSort {
     BlackElo,
     #[serde(rename = "ply_count")]
     PlyCount,
+    #[serde(rename = "white")]
+    WhiteName,
+    #[serde(rename = "black")]
+    BlackName,
+    /// Ascending puts white wins first, then black wins, draws and games
+    /// without a result.
+    #[serde(rename = "result")]
+    Result,
+    /// Ascending puts decisive games first, then draws and games without a
+    /// result. Games within each group are in id order.
+    #[serde(rename = "decisiveness")]
+    Decisiveness,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -657,7 +2540,9 @@ pub struct QueryOptions<SortT> {
     pub skip_count: bool,
     pub page: Option<i64>,
     pub page_size: Option<i64>,
+    #[serde(default)]
     pub sort: SortT,
+    #[serde(default)]
     pub direction: SortDirection,
 }
 
@@ -667,13 +2552,65 @@ pub struct GameQuery {
     pub player1: Option<i32>,
     pub player2: Option<i32>,
     pub tournament_id: Option<i32>,
+    /// Matches the Round header exactly, e.g. "5" or "3.2".
+    pub round: Option<String>,
+    /// Dates are compared as "YYYY.MM.DD" strings, the format of the PGN Date
+    /// tag. A game with an unknown month or day matches if any date it could
+    /// have been played on is in range. Games with an unknown year never match.
     pub start_date: Option<String>,
     pub end_date: Option<String>,
     pub range1: Option<(i32, i32)>,
     pub range2: Option<(i32, i32)>,
+    /// Bounds on the ratings of both players, or of either one with
+    /// [`RatingMatch::Either`], whoever they are. Both inclusive. Unrated
+    /// players are never within bounds.
+    pub min_rating: Option<i32>,
+    pub max_rating: Option<i32>,
+    #[serde(default)]
+    pub rating_match: RatingMatch,
     pub sides: Option<Sides>,
     pub outcome: Option<String>,
+    pub player_result: Option<PlayerResultQuery>,
+    /// Matches games whose ECO code starts with this prefix, e.g. "B9".
+    pub eco: Option<String>,
+    /// Bounds on the number of plies of the game, both inclusive.
+    pub min_plies: Option<i32>,
+    pub max_plies: Option<i32>,
+    /// `true` keeps only games from the standard starting position, `false`
+    /// only games from a custom one (Chess960, odds, thematic openings).
+    pub standard_start: Option<bool>,
+    /// Matches the variant of the game, e.g. "Standard" or "Chess960".
+    pub variant: Option<String>,
+    /// Matches the Annotator header exactly.
+    pub annotator: Option<String>,
+    /// Matches where the game was played, as derived from its Site header.
+    pub source: Option<Source>,
+    /// `true` keeps only games with comments or NAGs, which are stored when
+    /// importing with `keep_annotations`, and `false` only games without.
+    pub has_annotations: Option<bool>,
+    /// Matches games ending in the position with this hash, as computed by
+    /// find_games_by_final_position.
+    pub final_position_hash: Option<i64>,
+    /// Matches games with this tag, as added by add_tag.
+    pub tag: Option<String>,
+    /// Matches games ending with this material, written like "KRPvKR", with
+    /// either side having either color.
+    pub final_material: Option<String>,
+    /// `true` keeps only puzzles, games set up from a custom position with a
+    /// short solution, and `false` leaves them out.
+    pub is_puzzle: Option<bool>,
+    /// SQL LIKE pattern matched against the starting FEN of the game.
+    pub fen: Option<String>,
     pub position: Option<PositionQuery>,
+    /// Only the first `max_ply` plies of each game are searched for
+    /// `position`. Defaults to the whole game.
+    pub max_ply: Option<usize>,
+    /// Also match games moved to the trash by delete_db_game.
+    #[serde(default)]
+    pub include_deleted: bool,
+    /// Only match games starred with toggle_star.
+    #[serde(default)]
+    pub starred: bool,
 }
 
 impl GameQuery {
@@ -690,164 +2627,472 @@ impl GameQuery {
 pub struct QueryResponse<T> {
     pub data: T,
     pub count: Option<i64>,
+    /// Whether there are rows after this page, known even when the count is
+    /// skipped.
+    pub has_more: bool,
 }
 
-#[tauri::command]
-pub async fn get_games(
-    file: PathBuf,
-    query: GameQuery,
-    state: tauri::State<'_, AppState>,
-) -> Result<QueryResponse<Vec<NormalizedGame>>, Error> {
-    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+/// Escapes the wildcards of user input for a LIKE pattern matched with
+/// `ESCAPE '\'`, so that "%" and "_" are matched literally.
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
 
-    let mut count: Option<i64> = None;
-    let query_options = query.options.unwrap_or_default();
+/// Drops the extra row fetched past `page_size`, returning whether there was
+/// one.
+fn trim_page<T>(rows: &mut Vec<T>, page_size: Option<i64>) -> bool {
+    match page_size {
+        Some(size) if rows.len() as i64 > size => {
+            rows.truncate(size as usize);
+            true
+        }
+        _ => false,
+    }
+}
 
-    let (white_players, black_players) = diesel::alias!(players as white, players as black);
-    let mut sql_query = games::table
-        .inner_join(white_players.on(games::white_id.eq(white_players.field(players::id))))
-        .inner_join(black_players.on(games::black_id.eq(black_players.field(players::id))))
-        .inner_join(events::table.on(games::event_id.eq(events::id)))
-        .inner_join(sites::table.on(games::site_id.eq(sites::id)))
-        .into_boxed();
-    let mut count_query = games::table.into_boxed();
+type GameCondition<QS> = Box<dyn BoxableExpression<QS, Sqlite, SqlType = Nullable<Bool>>>;
 
-    // if let Some(speed) = query.speed {
-    //     sql_query = sql_query.filter(games::speed.eq(speed as i32));
-    //     count_query = count_query.filter(games::speed.eq(speed as i32));
-    // }
+sql_function!(fn replace(x: Nullable<Text>, from: Text, to: Text) -> Nullable<Text>);
+
+/// Combines the given conditions with `AND`. Returns `None` if there are none.
+fn and_all<QS: 'static>(conditions: Vec<GameCondition<QS>>) -> Option<GameCondition<QS>> {
+    conditions
+        .into_iter()
+        .reduce(|acc, condition| Box::new(acc.and(condition)) as GameCondition<QS>)
+}
 
-    if let Some(outcome) = query.outcome {
-        sql_query = sql_query.filter(games::result.eq(outcome.clone()));
-        count_query = count_query.filter(games::result.eq(outcome));
+/// Builds the condition matching games where `white` played with the white
+/// pieces and `black` with the black pieces, with their ratings inside the
+/// given ranges. Returns `None` if there is nothing to filter on.
+fn color_condition<QS: 'static>(
+    white: Option<i32>,
+    black: Option<i32>,
+    white_range: Option<(i32, i32)>,
+    black_range: Option<(i32, i32)>,
+) -> Option<GameCondition<QS>>
+where
+    games::white_id: SelectableExpression<QS>,
+    games::black_id: SelectableExpression<QS>,
+    games::white_elo: SelectableExpression<QS>,
+    games::black_elo: SelectableExpression<QS>,
+{
+    let mut conditions: Vec<GameCondition<QS>> = Vec::new();
+    if let Some(white) = white {
+        conditions.push(Box::new(games::white_id.eq(white).nullable()));
+    }
+    if let Some(black) = black {
+        conditions.push(Box::new(games::black_id.eq(black).nullable()));
+    }
+    if let Some((min, max)) = white_range {
+        conditions.push(Box::new(games::white_elo.between(min, max)));
     }
+    if let Some((min, max)) = black_range {
+        conditions.push(Box::new(games::black_elo.between(min, max)));
+    }
+    and_all(conditions)
+}
 
-    if let Some(start_date) = query.start_date {
-        sql_query = sql_query.filter(games::date.ge(start_date.clone()));
-        count_query = count_query.filter(games::date.ge(start_date));
+/// Builds the condition for the player and rating filters of a [`GameQuery`].
+/// `player1` and `range1` always refer to the same player, so with
+/// [`Sides::Any`] both color assignments are tried.
+fn sides_condition<QS: 'static>(
+    sides: &Sides,
+    player1: Option<i32>,
+    player2: Option<i32>,
+    range1: Option<(i32, i32)>,
+    range2: Option<(i32, i32)>,
+) -> Option<GameCondition<QS>>
+where
+    games::white_id: SelectableExpression<QS>,
+    games::black_id: SelectableExpression<QS>,
+    games::white_elo: SelectableExpression<QS>,
+    games::black_elo: SelectableExpression<QS>,
+{
+    match sides {
+        Sides::WhiteBlack => color_condition(player1, player2, range1, range2),
+        Sides::BlackWhite => color_condition(player2, player1, range2, range1),
+        Sides::Any => {
+            let as_white = color_condition(player1, player2, range1, range2)?;
+            let as_black = color_condition(player2, player1, range2, range1)?;
+            Some(Box::new(as_white.or(as_black)))
+        }
     }
+}
 
-    if let Some(end_date) = query.end_date {
-        sql_query = sql_query.filter(games::date.le(end_date.clone()));
-        count_query = count_query.filter(games::date.le(end_date));
+/// Applies the filters of a [`GameQuery`] to a query over the games table
+/// alone. Queries joining other tables use [`game_query_condition`] directly.
+fn apply_filters<'a, ST>(
+    sql_query: games::BoxedQuery<'a, Sqlite, ST>,
+    query: &GameQuery,
+) -> games::BoxedQuery<'a, Sqlite, ST> {
+    match game_query_condition(query) {
+        Some(condition) => sql_query.filter(condition),
+        None => sql_query,
     }
+}
 
-    if let Some(tournament_id) = query.tournament_id {
-        sql_query = sql_query.filter(games::event_id.eq(tournament_id));
-        count_query = count_query.filter(games::event_id.eq(tournament_id));
+/// Builds the condition matching the games selected by the filters of a
+/// [`GameQuery`], so that every query over games applies them the same way.
+/// Returns `None` if the query doesn't filter anything, which is only the case
+/// when it includes deleted games.
+fn game_query_condition<QS: 'static>(query: &GameQuery) -> Option<GameCondition<QS>>
+where
+    games::white_id: SelectableExpression<QS>,
+    games::black_id: SelectableExpression<QS>,
+    games::white_elo: SelectableExpression<QS>,
+    games::black_elo: SelectableExpression<QS>,
+    games::result: SelectableExpression<QS>,
+    games::eco: SelectableExpression<QS>,
+    games::date: SelectableExpression<QS>,
+    games::event_id: SelectableExpression<QS>,
+    games::ply_count: SelectableExpression<QS>,
+    games::round: SelectableExpression<QS>,
+    games::fen: SelectableExpression<QS>,
+    games::variant: SelectableExpression<QS>,
+    games::deleted: SelectableExpression<QS>,
+    games::annotator: SelectableExpression<QS>,
+    games::source: SelectableExpression<QS>,
+    games::final_position_hash: SelectableExpression<QS>,
+    games::is_puzzle: SelectableExpression<QS>,
+    games::final_material: SelectableExpression<QS>,
+    games::starred: SelectableExpression<QS>,
+    games::move_text: SelectableExpression<QS>,
+{
+    let mut conditions: Vec<GameCondition<QS>> = Vec::new();
+
+    if !query.include_deleted {
+        conditions.push(Box::new(games::deleted.eq(false).nullable()));
     }
 
-    if let Some(limit) = query_options.page_size {
-        sql_query = sql_query.limit(limit);
+    if query.starred {
+        conditions.push(Box::new(games::starred.eq(true).nullable()));
     }
 
-    if let Some(page) = query_options.page {
-        sql_query = sql_query.offset((page - 1) * query_options.page_size.unwrap_or(10));
+    // Games without a result are stored as NULL, or as "*" in older databases
+    match query.outcome.as_deref() {
+        Some("*") => conditions.push(Box::new(
+            games::result.is_null().nullable().or(games::result.eq("*")),
+        )),
+        Some(outcome) => conditions.push(Box::new(games::result.eq(outcome.to_string()))),
+        None => {}
     }
 
-    match query.sides {
-        Some(Sides::BlackWhite) => {
-            if let Some(player1) = query.player1 {
-                sql_query = sql_query.filter(games::black_id.eq(player1));
-                count_query = count_query.filter(games::black_id.eq(player1));
-            }
-            if let Some(player2) = query.player2 {
-                sql_query = sql_query.filter(games::white_id.eq(player2));
-                count_query = count_query.filter(games::white_id.eq(player2));
-            }
+    if let Some(PlayerResultQuery { player, result }) = query.player_result {
+        let as_white = games::white_id.eq(player).nullable();
+        let as_black = games::black_id.eq(player).nullable();
+        conditions.push(match result {
+            PlayerResult::Win => Box::new(
+                as_white
+                    .and(games::result.eq("1-0"))
+                    .or(as_black.and(games::result.eq("0-1"))),
+            ),
+            PlayerResult::Loss => Box::new(
+                as_white
+                    .and(games::result.eq("0-1"))
+                    .or(as_black.and(games::result.eq("1-0"))),
+            ),
+            PlayerResult::Draw => Box::new(as_white.or(as_black).and(games::result.eq("1/2-1/2"))),
+        });
+    }
 
-            if let Some(range1) = query.range1 {
-                sql_query = sql_query.filter(games::black_elo.between(range1.0, range1.1));
-                count_query = count_query.filter(games::black_elo.between(range1.0, range1.1));
-            }
+    if let Some(eco) = &query.eco {
+        conditions.push(Box::new(
+            games::eco
+                .like(format!("{}%", escape_like(eco)))
+                .escape('\\'),
+        ));
+    }
 
-            if let Some(range2) = query.range2 {
-                sql_query = sql_query.filter(games::white_elo.between(range2.0, range2.1));
-                count_query = count_query.filter(games::white_elo.between(range2.0, range2.1));
-            }
-        }
-        Some(Sides::WhiteBlack) => {
-            if let Some(player1) = query.player1 {
-                sql_query = sql_query.filter(games::white_id.eq(player1));
-                count_query = count_query.filter(games::white_id.eq(player1));
-            }
-            if let Some(player2) = query.player2 {
-                sql_query = sql_query.filter(games::black_id.eq(player2));
-                count_query = count_query.filter(games::black_id.eq(player2));
-            }
+    if query.start_date.is_some() || query.end_date.is_some() {
+        conditions.push(Box::new(games::date.not_like("????%")));
+    }
 
-            if let Some(range1) = query.range1 {
-                sql_query = sql_query.filter(games::white_elo.between(range1.0, range1.1));
-                count_query = count_query.filter(games::white_elo.between(range1.0, range1.1));
-            }
+    // '?' sorts after every digit, so an unknown component already compares as
+    // its latest possible value
+    if let Some(start_date) = &query.start_date {
+        conditions.push(Box::new(games::date.ge(start_date.clone())));
+    }
 
-            if let Some(range2) = query.range2 {
-                sql_query = sql_query.filter(games::black_elo.between(range2.0, range2.1));
-                count_query = count_query.filter(games::black_elo.between(range2.0, range2.1));
-            }
-        }
-        Some(Sides::Any) => {
-            if let Some(player1) = query.player1 {
-                sql_query =
-                    sql_query.filter(games::white_id.eq(player1).or(games::black_id.eq(player1)));
-                count_query =
-                    count_query.filter(games::white_id.eq(player1).or(games::black_id.eq(player1)));
-            }
-            if let Some(player2) = query.player2 {
-                sql_query =
-                    sql_query.filter(games::white_id.eq(player2).or(games::black_id.eq(player2)));
-                count_query =
-                    count_query.filter(games::white_id.eq(player2).or(games::black_id.eq(player2)));
-            }
+    if let Some(end_date) = &query.end_date {
+        conditions.push(Box::new(
+            replace(games::date, "?", "0").le(end_date.clone()),
+        ));
+    }
 
-            if let (Some(range1), Some(range2)) = (query.range1, query.range2) {
-                sql_query = sql_query.filter(
-                    games::white_elo
-                        .between(range1.0, range1.1)
-                        .or(games::black_elo.between(range1.0, range1.1))
-                        .or(games::white_elo
-                            .between(range2.0, range2.1)
-                            .or(games::black_elo.between(range2.0, range2.1))),
-                );
-                count_query = count_query.filter(
-                    games::white_elo
-                        .between(range1.0, range1.1)
-                        .or(games::black_elo.between(range1.0, range1.1))
-                        .or(games::white_elo
-                            .between(range2.0, range2.1)
-                            .or(games::black_elo.between(range2.0, range2.1))),
-                );
-            } else {
-                if let Some(range1) = query.range1 {
-                    sql_query = sql_query.filter(
-                        games::white_elo
-                            .between(range1.0, range1.1)
-                            .or(games::black_elo.between(range1.0, range1.1)),
-                    );
-                    count_query = count_query.filter(
-                        games::white_elo
-                            .between(range1.0, range1.1)
-                            .or(games::black_elo.between(range1.0, range1.1)),
-                    );
-                }
+    if let Some(tournament_id) = query.tournament_id {
+        conditions.push(Box::new(games::event_id.eq(tournament_id).nullable()));
+    }
 
-                if let Some(range2) = query.range2 {
-                    sql_query = sql_query.filter(
-                        games::white_elo
-                            .between(range2.0, range2.1)
-                            .or(games::black_elo.between(range2.0, range2.1)),
-                    );
-                    count_query = count_query.filter(
-                        games::white_elo
-                            .between(range2.0, range2.1)
-                            .or(games::black_elo.between(range2.0, range2.1)),
-                    );
-                }
-            }
-        }
-        None => {}
+    if let Some(round) = &query.round {
+        conditions.push(Box::new(games::round.eq(round.clone())));
     }
 
+    if let Some(min_plies) = query.min_plies {
+        conditions.push(Box::new(games::ply_count.ge(min_plies)));
+    }
+
+    if let Some(max_plies) = query.max_plies {
+        conditions.push(Box::new(games::ply_count.le(max_plies)));
+    }
+
+    if query.min_rating.is_some() || query.max_rating.is_some() {
+        let min = query.min_rating.unwrap_or(i32::MIN);
+        let max = query.max_rating.unwrap_or(i32::MAX);
+        // A NULL rating makes BETWEEN NULL, so unrated players never match
+        let white = games::white_elo.between(min, max);
+        let black = games::black_elo.between(min, max);
+        conditions.push(match query.rating_match {
+            RatingMatch::Both => Box::new(white.and(black)),
+            RatingMatch::Either => Box::new(white.or(black)),
+        });
+    }
+
+    match query.standard_start {
+        Some(true) => conditions.push(Box::new(games::fen.is_null().nullable())),
+        Some(false) => conditions.push(Box::new(games::fen.is_not_null().nullable())),
+        None => {}
+    }
+
+    if let Some(fen) = &query.fen {
+        conditions.push(Box::new(games::fen.like(fen.clone())));
+    }
+
+    if let Some(variant) = &query.variant {
+        conditions.push(Box::new(games::variant.eq(variant.clone())));
+    }
+
+    if let Some(annotator) = &query.annotator {
+        conditions.push(Box::new(games::annotator.eq(annotator.clone())));
+    }
+
+    if let Some(source) = query.source {
+        conditions.push(Box::new(games::source.eq(source.as_str())));
+    }
+
+    if let Some(tag) = &query.tag {
+        conditions.push(Box::new(
+            sql::<Bool>("Games.ID IN (SELECT GameID FROM GameTags WHERE Tag = ")
+                .bind::<Text, _>(tag.clone())
+                .sql(")")
+                .nullable(),
+        ));
+    }
+
+    if let Some(hash) = query.final_position_hash {
+        conditions.push(Box::new(games::final_position_hash.eq(hash)));
+    }
+
+    if let Some(material) = &query.final_material {
+        let material = material.trim().to_uppercase().replace('V', "v");
+        conditions.push(Box::new(
+            games::final_material.eq_any(vec![mirror_material(&material), material]),
+        ));
+    }
+
+    if let Some(is_puzzle) = query.is_puzzle {
+        conditions.push(Box::new(games::is_puzzle.eq(is_puzzle).nullable()));
+    }
+
+    if let Some(has_annotations) = query.has_annotations {
+        // Comments are written in braces and NAGs as "$n", neither of which
+        // appears in moves or variations
+        let annotated = games::move_text
+            .like("%{%")
+            .or(games::move_text.like("%$%"));
+        conditions.push(if has_annotations {
+            Box::new(annotated)
+        } else {
+            Box::new(games::move_text.is_null().nullable().or(not(annotated)))
+        });
+    }
+
+    // Player and rating filters apply regardless of color unless a side
+    // assignment was requested explicitly.
+    let sides = query.sides.clone().unwrap_or(Sides::Any);
+    if let Some(condition) = sides_condition(
+        &sides,
+        query.player1,
+        query.player2,
+        query.range1,
+        query.range2,
+    ) {
+        conditions.push(condition);
+    }
+
+    and_all(conditions)
+}
+
+#[tauri::command]
+pub async fn get_games(
+    file: PathBuf,
+    query: GameQuery,
+    state: tauri::State<'_, AppState>,
+) -> Result<QueryResponse<Vec<NormalizedGame>>, Error> {
+    let db = &mut get_db_read_only(&state, file.to_str().unwrap())?;
+    query_games(db, &query)
+}
+
+fn query_games(
+    db: &mut SqliteConnection,
+    query: &GameQuery,
+) -> Result<QueryResponse<Vec<NormalizedGame>>, Error> {
+    let query_options = query.options.clone().unwrap_or_default();
+
+    // Never return the whole database at once, even when no page size is given
+    let page_size = query_options
+        .page_size
+        .unwrap_or(DEFAULT_GAMES_PAGE_SIZE)
+        .clamp(1, MAX_GAMES_PAGE_SIZE);
+    let offset = query_options
+        .page
+        .map_or(0, |page| (page.max(1) - 1).saturating_mul(page_size));
+    let (games, has_more) = load_games(db, query, offset, page_size)?;
+
+    let count = if query_options.skip_count {
+        None
+    } else {
+        Some(count_matching_games(db, query)?)
+    };
+
+    Ok(QueryResponse {
+        data: games,
+        count,
+        has_more,
+    })
+}
+
+/// Finds the games that ended in the position given as a FEN, paged and
+/// sorted like get_games. Games are matched by the Zobrist hash of their final
+/// position, so this is much cheaper than a position search but can't find
+/// positions reached earlier in a game.
+#[tauri::command]
+pub async fn find_games_by_final_position(
+    file: PathBuf,
+    fen: String,
+    options: Option<QueryOptions<GameSort>>,
+    state: tauri::State<'_, AppState>,
+) -> Result<QueryResponse<Vec<NormalizedGame>>, Error> {
+    let db = &mut get_db_read_only(&state, file.to_str().unwrap())?;
+
+    let position: Chess = Fen::from_ascii(fen.trim().as_bytes())?
+        .into_position(CastlingMode::Chess960)
+        .or_else(PositionError::ignore_too_much_material)?;
+    let query = GameQuery {
+        options,
+        final_position_hash: Some(position_hash(&position)),
+        ..Default::default()
+    };
+    query_games(db, &query)
+}
+
+/// Lists the games of a player, as white or black, by id rather than by name,
+/// sorted like get_games.
+#[tauri::command]
+pub async fn get_player_games(
+    file: PathBuf,
+    id: i32,
+    limit: i64,
+    offset: i64,
+    sort: Option<GameSort>,
+    direction: Option<SortDirection>,
+    state: tauri::State<'_, AppState>,
+) -> Result<QueryResponse<Vec<NormalizedGame>>, Error> {
+    let db = &mut get_db_read_only(&state, file.to_str().unwrap())?;
+
+    let query = GameQuery {
+        options: Some(QueryOptions {
+            sort: sort.unwrap_or_default(),
+            direction: direction.unwrap_or_default(),
+            ..Default::default()
+        }),
+        player1: Some(id),
+        sides: Some(Sides::Any),
+        ..Default::default()
+    };
+    let (games, has_more) = load_games(db, &query, offset, limit)?;
+    let count = count_matching_games(db, &query)?;
+
+    Ok(QueryResponse {
+        data: games,
+        count: Some(count),
+        has_more,
+    })
+}
+
+/// Returns the last `limit` games added to the database, newest first. Unlike
+/// sorting by date, this follows the import order, which doesn't depend on
+/// the PGN dates being present or right.
+#[tauri::command]
+pub async fn get_recent_games(
+    file: PathBuf,
+    limit: i64,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<NormalizedGame>, Error> {
+    let db = &mut get_db_read_only(&state, file.to_str().unwrap())?;
+
+    let query = GameQuery {
+        options: Some(QueryOptions {
+            sort: GameSort::Id,
+            direction: SortDirection::Desc,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let (games, _) = load_games(db, &query, 0, limit)?;
+    Ok(games)
+}
+
+/// Counts the games matching `query` without loading any of them, for showing
+/// how many games match before paging through them. The paging options of the
+/// query are ignored.
+#[tauri::command]
+pub async fn count_games(
+    file: PathBuf,
+    query: GameQuery,
+    state: tauri::State<'_, AppState>,
+) -> Result<i64, Error> {
+    let db = &mut get_db_read_only(&state, file.to_str().unwrap())?;
+    count_matching_games(db, &query)
+}
+
+fn count_matching_games(db: &mut SqliteConnection, query: &GameQuery) -> Result<i64, Error> {
+    let count = apply_filters(games::table.into_boxed(), query)
+        .select(diesel::dsl::count(games::id))
+        .first(db)?;
+    Ok(count)
+}
+
+/// Loads up to `limit` games matching `query` after skipping `offset` of them,
+/// in the order set by its options. Also returns whether more games follow.
+/// The limit is kept within 1..=MAX_GAMES_PAGE_SIZE and the offset positive.
+fn load_games(
+    db: &mut SqliteConnection,
+    query: &GameQuery,
+    offset: i64,
+    limit: i64,
+) -> Result<(Vec<NormalizedGame>, bool), Error> {
+    let query_options = query.options.clone().unwrap_or_default();
+    // SQLite reads a negative limit as no limit at all
+    let limit = limit.clamp(1, MAX_GAMES_PAGE_SIZE);
+    let offset = offset.max(0);
+
+    let (white_players, black_players) = diesel::alias!(players as white, players as black);
+    let mut sql_query = games::table
+        .inner_join(white_players.on(games::white_id.eq(white_players.field(players::id))))
+        .inner_join(black_players.on(games::black_id.eq(black_players.field(players::id))))
+        .inner_join(events::table.on(games::event_id.eq(events::id)))
+        .inner_join(sites::table.on(games::site_id.eq(sites::id)))
+        .into_boxed();
+
+    if let Some(condition) = game_query_condition(query) {
+        sql_query = sql_query.filter(condition);
+    }
+
+    // Fetch one extra row to tell whether a next page exists
+    sql_query = sql_query.limit(limit + 1).offset(offset);
+
     sql_query = match query_options.sort {
         GameSort::Id => match query_options.direction {
             SortDirection::Asc => sql_query.order(games::id.asc()),
@@ -869,14 +3114,42 @@ pub async fn get_games(
             SortDirection::Asc => sql_query.order(games::ply_count.asc()),
             SortDirection::Desc => sql_query.order(games::ply_count.desc()),
         },
+        GameSort::WhiteName => match query_options.direction {
+            SortDirection::Asc => sql_query.order(white_players.field(players::name).asc()),
+            SortDirection::Desc => sql_query.order(white_players.field(players::name).desc()),
+        },
+        GameSort::BlackName => match query_options.direction {
+            SortDirection::Asc => sql_query.order(black_players.field(players::name).asc()),
+            SortDirection::Desc => sql_query.order(black_players.field(players::name).desc()),
+        },
+        GameSort::Result => {
+            let rank = sql::<Integer>(
+                "CASE Games.Result WHEN '1-0' THEN 1 WHEN '0-1' THEN 2 \
+                 WHEN '1/2-1/2' THEN 3 ELSE 4 END",
+            );
+            match query_options.direction {
+                SortDirection::Asc => sql_query.order(rank.asc()),
+                SortDirection::Desc => sql_query.order(rank.desc()),
+            }
+        }
+        GameSort::Decisiveness => {
+            let rank = sql::<Integer>(
+                "CASE WHEN Games.Result IN ('1-0', '0-1') THEN 1 \
+                 WHEN Games.Result = '1/2-1/2' THEN 2 ELSE 3 END",
+            );
+            match query_options.direction {
+                SortDirection::Asc => sql_query.order(rank.asc()),
+                SortDirection::Desc => sql_query.order(rank.desc()),
+            }
+        }
     };
 
-    if !query_options.skip_count {
-        count = Some(
-            count_query
-                .select(diesel::dsl::count(games::id))
-                .first(db)?,
-        );
+    // Break ties by id so pages stay consistent when many games share a value
+    if query_options.sort != GameSort::Id {
+        sql_query = match query_options.direction {
+            SortDirection::Asc => sql_query.then_order_by(games::id.asc()),
+            SortDirection::Desc => sql_query.then_order_by(games::id.desc()),
+        };
     }
 
     // println!(
@@ -884,13 +3157,57 @@ pub async fn get_games(
     //     diesel::debug_query::<diesel::sqlite::Sqlite, _>(&sql_query)
     // );
 
-    let games: Vec<(Game, Player, Player, Event, Site)> = sql_query.load(db)?;
-    let normalized_games = normalize_games(games);
+    let mut games: Vec<(Game, Player, Player, Event, Site)> = sql_query.load(db)?;
+    let has_more = trim_page(&mut games, Some(limit));
+    Ok((normalize_games(games), has_more))
+}
+
+#[tauri::command]
+pub async fn get_game(
+    file: PathBuf,
+    id: i32,
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<NormalizedGame>, Error> {
+    let db = &mut get_db_read_only(&state, file.to_str().unwrap())?;
 
-    Ok(QueryResponse {
-        data: normalized_games,
-        count,
-    })
+    let (white_players, black_players) = diesel::alias!(players as white, players as black);
+    let game: Option<(Game, Player, Player, Event, Site)> = games::table
+        .inner_join(white_players.on(games::white_id.eq(white_players.field(players::id))))
+        .inner_join(black_players.on(games::black_id.eq(black_players.field(players::id))))
+        .inner_join(events::table.on(games::event_id.eq(events::id)))
+        .inner_join(sites::table.on(games::site_id.eq(sites::id)))
+        .filter(games::id.eq(id))
+        .first(db)
+        .optional()?;
+
+    Ok(game.and_then(|game| normalize_games(vec![game]).pop()))
+}
+
+/// Returns the mainline of a game as a list of SAN moves, or `None` if there
+/// is no such game. Each move is replayed from the starting position, so
+/// corrupted moves are reported as an error instead of being skipped.
+#[tauri::command]
+pub async fn get_game_moves(
+    file: PathBuf,
+    id: i32,
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<Vec<String>>, Error> {
+    let db = &mut get_db_read_only(&state, file.to_str().unwrap())?;
+
+    let game: Option<(Vec<u8>, Option<String>)> = games::table
+        .filter(games::id.eq(id))
+        .select((games::moves, games::fen))
+        .first(db)
+        .optional()?;
+    let Some((moves, fen)) = game else {
+        return Ok(None);
+    };
+
+    let fen = match fen {
+        Some(fen) => Fen::from_ascii(fen.as_bytes())?,
+        None => Fen::default(),
+    };
+    Ok(Some(decode_moves(moves, fen)?))
 }
 
 fn normalize_games(games: Vec<(Game, Player, Player, Event, Site)>) -> Vec<NormalizedGame> {
@@ -899,7 +3216,7 @@ fn normalize_games(games: Vec<(Game, Player, Player, Event, Site)>) -> Vec<Norma
         .map(|(game, white, black, event, site)| {
             let fen: Fen = game
                 .fen
-                .map(|f| Fen::from_ascii(f.as_bytes()).unwrap())
+                .and_then(|f| Fen::from_ascii(f.as_bytes()).ok())
                 .unwrap_or_default();
 
             NormalizedGame {
@@ -917,11 +3234,18 @@ fn normalize_games(games: Vec<(Game, Player, Player, Event, Site)>) -> Vec<Norma
                 black: black.name.unwrap_or_default(),
                 black_id: game.black_id,
                 black_elo: game.black_elo,
-                result: game.result,
+                // Unfinished games are stored without a result
+                result: game.result.unwrap_or_else(|| "*".to_string()),
                 time_control: game.time_control,
                 eco: game.eco,
+                opening: game.opening,
                 white_material: game.white_material,
                 black_material: game.black_material,
+                move_text: game.move_text,
+                variant: game.variant,
+                annotator: game.annotator,
+                source: game.source,
+                starred: game.starred,
                 ply_count: game.ply_count,
                 fen: fen.to_string(),
                 moves: decode_moves(game.moves, fen).unwrap_or_default().join(" "),
@@ -934,10 +3258,13 @@ fn normalize_games(games: Vec<(Game, Player, Player, Event, Site)>) -> Vec<Norma
 pub struct PlayerQuery {
     pub options: QueryOptions<PlayerSort>,
     pub name: Option<String>,
+    /// Match `name` ignoring case and diacritics.
+    #[serde(default)]
+    pub normalized: bool,
     pub range: Option<(i32, i32)>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub enum PlayerSort {
     #[serde(rename = "id")]
     Id,
@@ -945,6 +3272,11 @@ pub enum PlayerSort {
     Name,
     #[serde(rename = "elo")]
     Elo,
+    /// Number of games of the player, so the most active players come first
+    /// with the default descending direction.
+    #[default]
+    #[serde(rename = "game_count")]
+    GameCount,
 }
 
 #[tauri::command]
@@ -954,7 +3286,7 @@ pub async fn get_player(
     id: i32,
     state: tauri::State<'_, AppState>,
 ) -> Result<Option<Player>, Error> {
-    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+    let db = &mut get_db_read_only(&state, file.to_str().unwrap())?;
     let player = players::table
         .filter(players::id.eq(id))
         .first::<Player>(db)
@@ -968,7 +3300,7 @@ pub async fn get_players(
     query: PlayerQuery,
     state: tauri::State<'_, AppState>,
 ) -> Result<QueryResponse<Vec<Player>>, Error> {
-    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+    let db = &mut get_db_read_only(&state, file.to_str().unwrap())?;
     let mut count = None;
 
     let mut sql_query = players::table.into_boxed();
@@ -976,9 +3308,17 @@ pub async fn get_players(
     sql_query = sql_query.filter(players::name.is_not("Unknown"));
     count_query = count_query.filter(players::name.is_not("Unknown"));
 
-    if let Some(name) = query.name {
-        sql_query = sql_query.filter(players::name.like(format!("%{}%", name)));
-        count_query = count_query.filter(players::name.like(format!("%{}%", name)));
+    if let Some(name) = &query.name {
+        if query.normalized {
+            let pattern = format!("%{}%", escape_like(&normalize_name(name)));
+            sql_query =
+                sql_query.filter(players::normalized_name.like(pattern.clone()).escape('\\'));
+            count_query = count_query.filter(players::normalized_name.like(pattern).escape('\\'));
+        } else {
+            let pattern = format!("%{}%", escape_like(name));
+            sql_query = sql_query.filter(players::name.like(pattern.clone()).escape('\\'));
+            count_query = count_query.filter(players::name.like(pattern).escape('\\'));
+        }
     }
 
     if let Some(range) = query.range {
@@ -991,7 +3331,7 @@ pub async fn get_players(
     }
 
     if let Some(limit) = query.options.page_size {
-        sql_query = sql_query.limit(limit);
+        sql_query = sql_query.limit(limit + 1);
     }
 
     if let Some(page) = query.options.page {
@@ -1011,18 +3351,195 @@ pub async fn get_players(
             SortDirection::Asc => sql_query.order(players::elo.asc()),
             SortDirection::Desc => sql_query.order(players::elo.desc()),
         },
+        PlayerSort::GameCount => {
+            let mut players = players_by_game_count(db, &query)?;
+            let has_more = trim_page(&mut players, query.options.page_size);
+            return Ok(QueryResponse {
+                data: players,
+                count,
+                has_more,
+            });
+        }
     };
 
-    let players = sql_query.load::<Player>(db)?;
+    let mut players = sql_query.load::<Player>(db)?;
+    let has_more = trim_page(&mut players, query.options.page_size);
 
     Ok(QueryResponse {
         data: players,
         count,
+        has_more,
+    })
+}
+
+/// Loads the players matching `query` sorted by their number of games, with
+/// one more than the page size to tell whether there are more. The games of
+/// all the players are counted at once in a grouped subquery, which is much
+/// faster than counting them for each player.
+fn players_by_game_count(
+    db: &mut SqliteConnection,
+    query: &PlayerQuery,
+) -> Result<Vec<Player>, Error> {
+    let mut sql_query = diesel::sql_query(
+        "SELECT Players.* FROM Players
+        LEFT JOIN (
+            SELECT Player, COUNT(*) AS GameCount
+            FROM (
+                SELECT WhiteID AS Player FROM Games WHERE NOT Deleted
+                UNION ALL
+                SELECT BlackID AS Player FROM Games WHERE NOT Deleted AND BlackID != WhiteID
+            )
+            GROUP BY Player
+        ) counts ON counts.Player = Players.ID
+        WHERE Players.Name IS NOT 'Unknown'",
+    )
+    .into_boxed::<Sqlite>();
+
+    if let Some(name) = &query.name {
+        if query.normalized {
+            sql_query = sql_query
+                .sql(" AND Players.NormalizedName LIKE ? ESCAPE '\\'")
+                .bind::<Text, _>(format!("%{}%", escape_like(&normalize_name(name))));
+        } else {
+            sql_query = sql_query
+                .sql(" AND Players.Name LIKE ? ESCAPE '\\'")
+                .bind::<Text, _>(format!("%{}%", escape_like(name)));
+        }
+    }
+
+    if let Some(range) = query.range {
+        sql_query = sql_query
+            .sql(" AND Players.Elo BETWEEN ? AND ?")
+            .bind::<Integer, _>(range.0)
+            .bind::<Integer, _>(range.1);
+    }
+
+    sql_query = match query.options.direction {
+        SortDirection::Asc => sql_query.sql(" ORDER BY COALESCE(counts.GameCount, 0) ASC"),
+        SortDirection::Desc => sql_query.sql(" ORDER BY COALESCE(counts.GameCount, 0) DESC"),
+    };
+
+    // SQLite needs a LIMIT for an OFFSET, where -1 means no limit
+    let page_size = query.options.page_size;
+    let offset = query
+        .options
+        .page
+        .map_or(0, |page| (page - 1) * page_size.unwrap_or(10));
+    let players = sql_query
+        .sql(" LIMIT ? OFFSET ?")
+        .bind::<BigInt, _>(page_size.map_or(-1, |limit| limit + 1))
+        .bind::<BigInt, _>(offset)
+        .load::<Player>(db)?;
+    Ok(players)
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum PlayerRanking {
+    /// Most games played.
+    Activity,
+    /// Highest rating reached in any game.
+    Rating,
+}
+
+#[derive(QueryableByName, Debug, Serialize)]
+pub struct RankedPlayer {
+    #[diesel(sql_type = Integer)]
+    pub id: i32,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub name: Option<String>,
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub elo: Option<i32>,
+    /// Number of games or peak rating, depending on the ranking.
+    #[diesel(sql_type = BigInt)]
+    pub value: i64,
+}
+
+#[tauri::command]
+pub async fn get_top_players(
+    file: PathBuf,
+    by: PlayerRanking,
+    limit: i64,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<RankedPlayer>, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+
+    let value = match by {
+        PlayerRanking::Activity => "COUNT(*)",
+        PlayerRanking::Rating => "MAX(g.Rating)",
+    };
+    let players = sql_query(format!(
+        "SELECT p.ID AS id, p.Name AS name, p.Elo AS elo, {value} AS value
+        FROM (
+            SELECT WhiteID AS Player, WhiteElo AS Rating FROM Games WHERE NOT Deleted
+            UNION ALL
+            SELECT BlackID AS Player, BlackElo AS Rating FROM Games WHERE NOT Deleted
+        ) g
+        JOIN Players p ON p.ID = g.Player
+        WHERE p.ID != 0
+        GROUP BY p.ID
+        HAVING value IS NOT NULL
+        ORDER BY value DESC
+        LIMIT ?;"
+    ))
+    .bind::<BigInt, _>(limit)
+    .load(db)?;
+
+    Ok(players)
+}
+
+#[derive(QueryableByName, Debug, Serialize)]
+pub struct Opponent {
+    #[diesel(sql_type = Integer)]
+    pub id: i32,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub name: Option<String>,
+    #[diesel(sql_type = BigInt)]
+    pub games: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OpponentStats {
+    /// Number of distinct opponents.
+    pub count: usize,
+    /// Opponent with the most games against the player.
+    pub most_faced: Option<Opponent>,
+}
+
+/// Counts the distinct opponents of a player. Unknown opponents aren't
+/// counted.
+#[tauri::command]
+pub async fn get_opponent_count(
+    file: PathBuf,
+    id: i32,
+    state: tauri::State<'_, AppState>,
+) -> Result<OpponentStats, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+
+    let opponents: Vec<Opponent> = sql_query(
+        "SELECT p.ID AS id, p.Name AS name, COUNT(*) AS games
+        FROM (
+            SELECT BlackID AS Opponent FROM Games WHERE WhiteID = ? AND NOT Deleted
+            UNION ALL
+            SELECT WhiteID AS Opponent FROM Games WHERE BlackID = ? AND NOT Deleted
+        ) g
+        JOIN Players p ON p.ID = g.Opponent
+        WHERE p.ID != 0
+        GROUP BY p.ID
+        ORDER BY games DESC, p.ID;",
+    )
+    .bind::<Integer, _>(id)
+    .bind::<Integer, _>(id)
+    .load(db)?;
+
+    Ok(OpponentStats {
+        count: opponents.len(),
+        most_faced: opponents.into_iter().next(),
     })
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub enum TournamentSort {
+    #[default]
     #[serde(rename = "id")]
     Id,
     #[serde(rename = "name")]
@@ -1035,13 +3552,75 @@ pub struct TournamentQuery {
     pub name: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct EventSummary {
+    pub id: i32,
+    pub name: String,
+    pub game_count: i64,
+    /// Earliest and latest dates of the games of the event, ignoring games
+    /// with an unknown year.
+    pub first_date: Option<String>,
+    pub last_date: Option<String>,
+}
+
+/// Lists the events of a database with their number of games, sorted by name.
+/// Unnamed events aren't listed.
+#[tauri::command]
+pub async fn list_events(
+    file: PathBuf,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<EventSummary>, Error> {
+    let db = &mut get_db_read_only(&state, file.to_str().unwrap())?;
+
+    let rows: Vec<(i32, Option<String>, Option<String>, i64)> = games::table
+        .inner_join(events::table)
+        .filter(games::deleted.eq(false))
+        .filter(events::name.is_not("Unknown").and(events::name.is_not("")))
+        .group_by((events::id, events::name, games::date))
+        .select((
+            events::id,
+            events::name,
+            games::date,
+            diesel::dsl::count_star(),
+        ))
+        .load(db)?;
+
+    let mut events: BTreeMap<(String, i32), EventSummary> = BTreeMap::new();
+    for (id, name, date, count) in rows {
+        let Some(name) = name else {
+            continue;
+        };
+        let event = events
+            .entry((name.clone(), id))
+            .or_insert_with(|| EventSummary {
+                id,
+                name,
+                game_count: 0,
+                first_date: None,
+                last_date: None,
+            });
+        event.game_count += count;
+        if let Some(date) = date.filter(|date| !date.starts_with("????")) {
+            let first = event.first_date.get_or_insert_with(|| date.clone());
+            if date < *first {
+                *first = date.clone();
+            }
+            let last = event.last_date.get_or_insert_with(|| date.clone());
+            if date > *last {
+                *last = date;
+            }
+        }
+    }
+    Ok(events.into_values().collect())
+}
+
 #[tauri::command]
 pub async fn get_tournaments(
     file: PathBuf,
     query: TournamentQuery,
     state: tauri::State<'_, AppState>,
 ) -> Result<QueryResponse<Vec<Event>>, Error> {
-    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+    let db = &mut get_db_read_only(&state, file.to_str().unwrap())?;
     let mut count = None;
 
     let mut sql_query = events::table.into_boxed();
@@ -1050,8 +3629,9 @@ pub async fn get_tournaments(
     count_query = count_query.filter(events::name.is_not("Unknown").and(events::name.is_not("")));
 
     if let Some(name) = query.name {
-        sql_query = sql_query.filter(events::name.like(format!("%{}%", name)));
-        count_query = count_query.filter(events::name.like(format!("%{}%", name)));
+        let pattern = format!("%{}%", escape_like(&name));
+        sql_query = sql_query.filter(events::name.like(pattern.clone()).escape('\\'));
+        count_query = count_query.filter(events::name.like(pattern).escape('\\'));
     }
 
     if !query.options.skip_count {
@@ -1059,7 +3639,7 @@ pub async fn get_tournaments(
     }
 
     if let Some(limit) = query.options.page_size {
-        sql_query = sql_query.limit(limit);
+        sql_query = sql_query.limit(limit + 1);
     }
 
     if let Some(page) = query.options.page {
@@ -1077,11 +3657,13 @@ pub async fn get_tournaments(
         },
     };
 
-    let events = sql_query.load::<Event>(db)?;
+    let mut events = sql_query.load::<Event>(db)?;
+    let has_more = trim_page(&mut events, query.options.page_size);
 
     Ok(QueryResponse {
         data: events,
         count,
+        has_more,
     })
 }
 
@@ -1090,6 +3672,10 @@ pub struct PlayerGameInfo {
     pub won: i32,
     pub lost: i32,
     pub draw: i32,
+    /// Results of the player with the white pieces.
+    pub white_results: Results,
+    /// Results of the player with the black pieces.
+    pub black_results: Results,
     pub data_per_month: Vec<(String, MonthData)>,
     pub white_openings: Vec<(String, Results)>,
     pub black_openings: Vec<(String, Results)>,
@@ -1102,6 +3688,15 @@ pub struct Results {
     pub draw: i32,
 }
 
+impl Results {
+    /// Share of the points scored, counting draws as half a point, or `None`
+    /// if there are no results.
+    pub fn score(&self) -> Option<f64> {
+        let games = self.won + self.lost + self.draw;
+        (games > 0).then(|| (self.won as f64 + self.draw as f64 / 2.0) / games as f64)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Type, Default)]
 pub struct MonthData {
     pub count: i32,
@@ -1138,7 +3733,8 @@ pub async fn get_players_game_info(
             games::black_elo,
         ))
         .filter(games::white_id.eq(id).or(games::black_id.eq(id)))
-        .filter(games::fen.is_null());
+        .filter(games::fen.is_null())
+        .filter(games::deleted.eq(false));
 
     type GameInfo = (
         i32,
@@ -1154,9 +3750,10 @@ pub async fn get_players_game_info(
     let mut game_info = PlayerGameInfo::default();
     let white_openings = DashMap::new();
     let black_openings = DashMap::new();
-    let won = AtomicI32::new(0);
-    let lost = AtomicI32::new(0);
-    let draw = AtomicI32::new(0);
+    // results per color, white first
+    let won = [AtomicI32::new(0), AtomicI32::new(0)];
+    let lost = [AtomicI32::new(0), AtomicI32::new(0)];
+    let draw = [AtomicI32::new(0), AtomicI32::new(0)];
     let data_per_month = DashMap::new();
     let progress = AtomicUsize::new(0);
 
@@ -1172,7 +3769,9 @@ pub async fn get_players_game_info(
                     // max length of opening in data
                     break;
                 }
-                let m = decode_move(*byte, &chess).unwrap();
+                let Some(m) = decode_move(*byte, &chess) else {
+                    break;
+                };
                 chess.play_unchecked(&m);
                 setups.push(chess.clone().into_setup(EnPassantMode::Legal));
             }
@@ -1248,16 +3847,17 @@ pub async fn get_players_game_info(
                     month_data.avg_count += 1;
                 }
             }
+            let color = usize::from(!is_white);
             match outcome.as_deref() {
                 Some("1-0") => match is_white {
-                    true => won.fetch_add(1, Ordering::Relaxed),
-                    false => lost.fetch_add(1, Ordering::Relaxed),
+                    true => won[color].fetch_add(1, Ordering::Relaxed),
+                    false => lost[color].fetch_add(1, Ordering::Relaxed),
                 },
                 Some("0-1") => match is_white {
-                    true => lost.fetch_add(1, Ordering::Relaxed),
-                    false => won.fetch_add(1, Ordering::Relaxed),
+                    true => lost[color].fetch_add(1, Ordering::Relaxed),
+                    false => won[color].fetch_add(1, Ordering::Relaxed),
                 },
-                Some("1/2-1/2") => draw.fetch_add(1, Ordering::Relaxed),
+                Some("1/2-1/2") => draw[color].fetch_add(1, Ordering::Relaxed),
                 _ => 0,
             };
 
@@ -1273,9 +3873,22 @@ pub async fn get_players_game_info(
     );
     game_info.white_openings = white_openings.into_iter().collect();
     game_info.black_openings = black_openings.into_iter().collect();
-    game_info.won = won.into_inner();
-    game_info.lost = lost.into_inner();
-    game_info.draw = draw.into_inner();
+    let [white_won, black_won] = won.map(AtomicI32::into_inner);
+    let [white_lost, black_lost] = lost.map(AtomicI32::into_inner);
+    let [white_draw, black_draw] = draw.map(AtomicI32::into_inner);
+    game_info.white_results = Results {
+        won: white_won,
+        lost: white_lost,
+        draw: white_draw,
+    };
+    game_info.black_results = Results {
+        won: black_won,
+        lost: black_lost,
+        draw: black_draw,
+    };
+    game_info.won = white_won + black_won;
+    game_info.lost = white_lost + black_lost;
+    game_info.draw = white_draw + black_draw;
     game_info.data_per_month = data_per_month.into_iter().collect();
     game_info.data_per_month = game_info
         .data_per_month
@@ -1306,58 +3919,1360 @@ pub async fn get_players_game_info(
     Ok(game_info)
 }
 
-#[tauri::command]
-pub async fn delete_database(
-    file: PathBuf,
-    state: tauri::State<'_, AppState>,
-) -> Result<(), Error> {
-    let pool = &state.connection_pool;
-    let path_str = file.to_str().unwrap();
-    pool.remove(path_str);
-
-    // delete file
-    remove_file(path_str)?;
-    Ok(())
+#[derive(QueryableByName, Debug)]
+struct PlayerResultCount {
+    #[diesel(sql_type = Integer)]
+    player_id: i32,
+    #[diesel(sql_type = Bool)]
+    is_white: bool,
+    #[diesel(sql_type = Nullable<Text>)]
+    result: Option<String>,
+    #[diesel(sql_type = BigInt)]
+    games: i64,
 }
 
+/// Counts the wins, losses and draws of many players at once, overall and per
+/// color, for showing their records in a list without a query per player.
+/// Only the result fields of each PlayerGameInfo are filled; use
+/// get_players_game_info for the openings and monthly data of one player.
+/// Players without games get empty results.
 #[tauri::command]
-pub async fn delete_duplicated_games(
+pub async fn get_players_game_info_bulk(
     file: PathBuf,
+    ids: Vec<i32>,
     state: tauri::State<'_, AppState>,
-) -> Result<(), Error> {
-    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
-
-    db.batch_execute(
-        "
-        DELETE FROM Games
-        WHERE ID IN (
-            SELECT ID
-            FROM (
-                SELECT ID,
-                    ROW_NUMBER() OVER (PARTITION BY EventID, SiteID, Round, WhiteID, BlackID, Moves, Date, UTCTime ORDER BY ID) AS RowNum
-                FROM Games
-            ) AS Subquery
-            WHERE RowNum > 1
-        );
-        ",
-    )?;
+) -> Result<HashMap<i32, PlayerGameInfo>, Error> {
+    let db = &mut get_db_read_only(&state, file.to_str().unwrap())?;
+    players_game_info_bulk(db, &ids)
+}
 
-    Ok(())
+fn players_game_info_bulk(
+    db: &mut SqliteConnection,
+    ids: &[i32],
+) -> Result<HashMap<i32, PlayerGameInfo>, Error> {
+    // The ids are bound as a single JSON array and expanded with json_each
+    let counts: Vec<PlayerResultCount> = sql_query(
+        "SELECT PlayerID AS player_id, IsWhite AS is_white, Result AS result,
+            COUNT(*) AS games
+        FROM (
+            SELECT WhiteID AS PlayerID, 1 AS IsWhite, Result FROM Games
+            WHERE Deleted = 0 AND WhiteID IN (SELECT value FROM json_each(?1))
+            UNION ALL
+            SELECT BlackID AS PlayerID, 0 AS IsWhite, Result FROM Games
+            WHERE Deleted = 0 AND BlackID IN (SELECT value FROM json_each(?1))
+        )
+        GROUP BY PlayerID, IsWhite, Result;",
+    )
+    .bind::<Text, _>(serde_json::to_string(ids)?)
+    .load(db)?;
+
+    let mut infos: HashMap<i32, PlayerGameInfo> = ids
+        .iter()
+        .map(|&id| (id, PlayerGameInfo::default()))
+        .collect();
+    for count in counts {
+        let info = infos.entry(count.player_id).or_default();
+        let results = if count.is_white {
+            &mut info.white_results
+        } else {
+            &mut info.black_results
+        };
+        let games = i32::try_from(count.games).unwrap_or(i32::MAX);
+        let total = match (count.result.as_deref(), count.is_white) {
+            (Some("1-0"), true) | (Some("0-1"), false) => &mut results.won,
+            (Some("1-0"), false) | (Some("0-1"), true) => &mut results.lost,
+            (Some("1/2-1/2"), _) => &mut results.draw,
+            _ => continue,
+        };
+        *total = total.saturating_add(games);
+    }
+    for info in infos.values_mut() {
+        info.won = info
+            .white_results
+            .won
+            .saturating_add(info.black_results.won);
+        info.lost = info
+            .white_results
+            .lost
+            .saturating_add(info.black_results.lost);
+        info.draw = info
+            .white_results
+            .draw
+            .saturating_add(info.black_results.draw);
+    }
+    Ok(infos)
 }
 
+/// Plies replayed when looking for the opening of a game, enough to cover the
+/// longest lines of the opening table.
+const CLASSIFY_MAX_PLIES: usize = 40;
+
+/// Fills in the ECO code and opening name of the games that have no ECO
+/// header, from the deepest known opening position they reach. Returns the
+/// number of games classified.
 #[tauri::command]
-pub async fn delete_empty_games(
+pub async fn classify_openings(
     file: PathBuf,
     state: tauri::State<'_, AppState>,
-) -> Result<(), Error> {
+) -> Result<usize, Error> {
     let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
 
-    diesel::delete(games::table.filter(games::ply_count.eq(0))).execute(db)?;
+    // Only games from the standard starting position can match the table
+    let unclassified: Vec<(i32, Vec<u8>)> = games::table
+        .select((games::id, games::moves))
+        .filter(games::eco.is_null())
+        .filter(games::fen.is_null())
+        .load(db)?;
 
-    Ok(())
-}
+    let classified: Vec<(i32, &str, &str)> = unclassified
+        .par_iter()
+        .filter_map(|(id, moves)| {
+            let mut chess = Chess::default();
+            let mut opening = None;
+            for byte in moves.iter().take(CLASSIFY_MAX_PLIES) {
+                let Some(m) = decode_move(*byte, &chess) else {
+                    break;
+                };
+                chess.play_unchecked(&m);
+                let setup = chess.clone().into_setup(EnPassantMode::Legal);
+                if let Some(found) = get_eco_from_setup(&setup) {
+                    opening = Some(found);
+                }
+            }
+            opening.map(|(eco, name)| (*id, eco, name))
+        })
+        .collect();
 
-struct PgnGame {
+    db.transaction::<_, Error, _>(|db| {
+        for (id, eco, name) in &classified {
+            diesel::update(games::table.filter(games::id.eq(id)))
+                .set((games::eco.eq(eco), games::opening.eq(name)))
+                .execute(db)?;
+        }
+        if !classified.is_empty() {
+            touch_database(db)?;
+        }
+        Ok(())
+    })?;
+
+    Ok(classified.len())
+}
+
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct HeadToHead {
+    pub player1_wins: i32,
+    pub player2_wins: i32,
+    pub draws: i32,
+    /// Results of player1 when playing with the white pieces.
+    pub player1_white: Results,
+    /// Results of player1 when playing with the black pieces.
+    pub player1_black: Results,
+}
+
+impl HeadToHead {
+    /// Tallies the results of the games between two players, given as the id
+    /// of the white player and the result of each game.
+    fn from_games(player1: i32, games: &[(i32, Option<String>)]) -> Self {
+        let mut stats = HeadToHead::default();
+        for (white_id, result) in games {
+            let is_white = *white_id == player1;
+            let results = if is_white {
+                &mut stats.player1_white
+            } else {
+                &mut stats.player1_black
+            };
+            match (result.as_deref(), is_white) {
+                (Some("1-0"), true) | (Some("0-1"), false) => {
+                    stats.player1_wins += 1;
+                    results.won += 1;
+                }
+                (Some("1-0"), false) | (Some("0-1"), true) => {
+                    stats.player2_wins += 1;
+                    results.lost += 1;
+                }
+                (Some("1/2-1/2"), _) => {
+                    stats.draws += 1;
+                    results.draw += 1;
+                }
+                _ => {}
+            }
+        }
+        stats
+    }
+}
+
+/// Returns the record between two players. Players that never met get zeroed
+/// stats.
+#[tauri::command]
+pub async fn get_head_to_head(
+    file: PathBuf,
+    id1: i32,
+    id2: i32,
+    state: tauri::State<'_, AppState>,
+) -> Result<HeadToHead, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+
+    let games: Vec<(i32, Option<String>)> = games::table
+        .select((games::white_id, games::result))
+        .filter(
+            games::white_id
+                .eq(id1)
+                .and(games::black_id.eq(id2))
+                .or(games::white_id.eq(id2).and(games::black_id.eq(id1))),
+        )
+        .filter(games::deleted.eq(false))
+        .load(db)?;
+
+    Ok(HeadToHead::from_games(id1, &games))
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlayerColor {
+    White,
+    Black,
+}
+
+#[derive(QueryableByName, Debug)]
+struct OpeningRow {
+    #[diesel(sql_type = Text)]
+    eco: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    opening: Option<String>,
+    #[diesel(sql_type = BigInt)]
+    games: i64,
+    #[diesel(sql_type = BigInt)]
+    white_wins: i64,
+    #[diesel(sql_type = BigInt)]
+    black_wins: i64,
+    #[diesel(sql_type = BigInt)]
+    draws: i64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct OpeningStats {
+    pub eco: String,
+    /// One of the opening names stored for this ECO code, if any.
+    pub opening: Option<String>,
+    pub games: i32,
+    /// Results from the point of view of the player.
+    pub results: Results,
+}
+
+/// Groups the games a player played with the given color by ECO code, most
+/// played first.
+#[tauri::command]
+pub async fn get_player_openings(
+    file: PathBuf,
+    id: i32,
+    color: PlayerColor,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<OpeningStats>, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+    player_openings(db, id, color)
+}
+
+fn player_openings(
+    db: &mut SqliteConnection,
+    id: i32,
+    color: PlayerColor,
+) -> Result<Vec<OpeningStats>, Error> {
+    let player_column = match color {
+        PlayerColor::White => "WhiteID",
+        PlayerColor::Black => "BlackID",
+    };
+    let rows: Vec<OpeningRow> = sql_query(format!(
+        "SELECT ECO AS eco, MAX(Opening) AS opening, COUNT(*) AS games,
+            SUM(Result IS '1-0') AS white_wins,
+            SUM(Result IS '0-1') AS black_wins,
+            SUM(Result IS '1/2-1/2') AS draws
+        FROM Games
+        WHERE {player_column} = ? AND ECO IS NOT NULL AND NOT Deleted
+        GROUP BY ECO
+        ORDER BY games DESC;"
+    ))
+    .bind::<Integer, _>(id)
+    .load(db)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let (won, lost) = match color {
+                PlayerColor::White => (row.white_wins, row.black_wins),
+                PlayerColor::Black => (row.black_wins, row.white_wins),
+            };
+            OpeningStats {
+                eco: row.eco,
+                opening: row.opening,
+                games: row.games as i32,
+                results: Results {
+                    won: won as i32,
+                    lost: lost as i32,
+                    draw: row.draws as i32,
+                },
+            }
+        })
+        .collect())
+}
+
+#[derive(QueryableByName, Debug)]
+struct NextMoveRow {
+    #[diesel(sql_type = Binary)]
+    next: Vec<u8>,
+    #[diesel(sql_type = BigInt)]
+    games: i64,
+    #[diesel(sql_type = BigInt)]
+    white_wins: i64,
+    #[diesel(sql_type = BigInt)]
+    black_wins: i64,
+    #[diesel(sql_type = BigInt)]
+    draws: i64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MoveTreeEntry {
+    pub san: String,
+    pub games: i64,
+    pub white_wins: i64,
+    pub black_wins: i64,
+    pub draws: i64,
+}
+
+/// Most continuations get_move_tree returns, whatever the limit asked for.
+const MAX_MOVE_TREE_BRANCHES: i64 = 50;
+
+/// Longest line get_move_tree explores, in plies.
+const MAX_MOVE_TREE_PLIES: usize = 60;
+
+/// Counts the moves played after the SAN moves in `moves` from the standard
+/// starting position, with the results of the games that followed each, most
+/// played first. A local opening explorer over the games of the database.
+/// Games are matched by the prefix of their encoded moves, so games from a
+/// custom position and games of other variants are left out.
+#[tauri::command]
+pub async fn get_move_tree(
+    file: PathBuf,
+    moves: Vec<String>,
+    limit: Option<i64>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<MoveTreeEntry>, Error> {
+    let db = &mut get_db_read_only(&state, file.to_str().unwrap())?;
+    move_tree(db, &moves, limit)
+}
+
+fn move_tree(
+    db: &mut SqliteConnection,
+    moves: &[String],
+    limit: Option<i64>,
+) -> Result<Vec<MoveTreeEntry>, Error> {
+    if moves.len() > MAX_MOVE_TREE_PLIES {
+        return Err(Error::LineTooLong(MAX_MOVE_TREE_PLIES));
+    }
+
+    let mut position = Chess::default();
+    let mut prefix = Vec::with_capacity(moves.len());
+    for san in moves {
+        let m = SanPlus::from_ascii(san.trim().as_bytes())?
+            .san
+            .to_move(&position)?;
+        prefix.push(encode_move(&m, &position)?);
+        position.play_unchecked(&m);
+    }
+
+    let limit = limit
+        .unwrap_or(MAX_MOVE_TREE_BRANCHES)
+        .clamp(1, MAX_MOVE_TREE_BRANCHES);
+    let rows: Vec<NextMoveRow> = sql_query(
+        "SELECT substr(Moves, ?1 + 1, 1) AS next, COUNT(*) AS games,
+            SUM(Result IS '1-0') AS white_wins,
+            SUM(Result IS '0-1') AS black_wins,
+            SUM(Result IS '1/2-1/2') AS draws
+        FROM Games
+        WHERE FEN IS NULL AND Deleted = 0
+            AND (Variant IS NULL OR Variant = 'Standard' COLLATE NOCASE)
+            AND length(Moves) > ?1 AND substr(Moves, 1, ?1) = ?2
+        GROUP BY next
+        ORDER BY games DESC
+        LIMIT ?3;",
+    )
+    .bind::<Integer, _>(prefix.len() as i32)
+    .bind::<Binary, _>(prefix)
+    .bind::<BigInt, _>(limit)
+    .load(db)?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            let m = decode_move(*row.next.first()?, &position)?;
+            Some(MoveTreeEntry {
+                san: SanPlus::from_move(position.clone(), &m).to_string(),
+                games: row.games,
+                white_wins: row.white_wins,
+                black_wins: row.black_wins,
+                draws: row.draws,
+            })
+        })
+        .collect())
+}
+
+/// Returns the openings where a player scores below 50% with the given color,
+/// worst first, as a list of what to study. Openings played fewer than
+/// `min_games` times with a result are left out, as their score says little.
+#[tauri::command]
+pub async fn get_weakest_openings(
+    file: PathBuf,
+    id: i32,
+    color: PlayerColor,
+    min_games: i32,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<OpeningStats>, Error> {
+    let db = &mut get_db_read_only(&state, file.to_str().unwrap())?;
+
+    let mut openings: Vec<(f64, OpeningStats)> = player_openings(db, id, color)?
+        .into_iter()
+        .filter_map(|stats| {
+            let score = stats.results.score()?;
+            let finished = stats.results.won + stats.results.lost + stats.results.draw;
+            (finished >= min_games.max(1) && score < 0.5).then_some((score, stats))
+        })
+        .collect();
+    openings.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+    Ok(openings.into_iter().map(|(_, stats)| stats).collect())
+}
+
+/// Rating difference for each score percentage from 50% to 100%, as given by
+/// the FIDE rating regulations.
+const FIDE_RATING_DIFFERENCES: [i32; 51] = [
+    0, 7, 14, 21, 29, 36, 43, 50, 57, 65, 72, 80, 87, 95, 102, 110, 117, 125, 133, 141, 149, 158,
+    166, 175, 184, 193, 202, 211, 220, 230, 240, 251, 262, 273, 284, 296, 309, 322, 336, 351, 366,
+    383, 401, 422, 444, 470, 501, 538, 589, 677, 800,
+];
+
+/// Returns the rating difference corresponding to a score between 0 and 1.
+fn rating_difference(score: f64) -> i32 {
+    let percentage = ((score * 100.0).round() as i32).clamp(0, 100);
+    if percentage >= 50 {
+        FIDE_RATING_DIFFERENCES[(percentage - 50) as usize]
+    } else {
+        -FIDE_RATING_DIFFERENCES[(50 - percentage) as usize]
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct PerformanceRating {
+    /// `None` if there are no games against rated opponents.
+    pub performance: Option<i32>,
+    pub average_opponent: Option<i32>,
+    pub score: f64,
+    /// Number of games the performance is based on.
+    pub games: usize,
+}
+
+impl PerformanceRating {
+    /// Computes the performance from the opponent's rating and the points
+    /// scored in each game.
+    fn from_results(results: &[(i32, f64)]) -> Self {
+        if results.is_empty() {
+            return PerformanceRating::default();
+        }
+
+        let games = results.len();
+        let average_opponent = results
+            .iter()
+            .map(|(rating, _)| *rating as f64)
+            .sum::<f64>()
+            / games as f64;
+        let score = results.iter().map(|(_, points)| points).sum::<f64>() / games as f64;
+
+        PerformanceRating {
+            performance: Some(average_opponent.round() as i32 + rating_difference(score)),
+            average_opponent: Some(average_opponent.round() as i32),
+            score,
+            games,
+        }
+    }
+}
+
+/// Returns the thresholds used to classify the games of a database by speed,
+/// as set by reclassify_speeds, or the Lichess ones.
+fn get_speed_thresholds(conn: &mut SqliteConnection) -> Result<SpeedThresholds, Error> {
+    let thresholds = info::table
+        .filter(info::name.eq("SpeedThresholds"))
+        .select(info::value)
+        .first::<Option<String>>(conn)
+        .optional()?
+        .flatten();
+    Ok(thresholds
+        .and_then(|thresholds| serde_json::from_str(&thresholds).ok())
+        .unwrap_or_default())
+}
+
+fn speed_distribution(conn: &mut SqliteConnection) -> Result<Vec<(Speed, i64)>, Error> {
+    let thresholds = get_speed_thresholds(conn)?;
+    let time_controls: Vec<(Option<String>, i64)> = games::table
+        .filter(games::deleted.eq(false))
+        .group_by(games::time_control)
+        .select((games::time_control, diesel::dsl::count_star()))
+        .load(conn)?;
+
+    let mut distribution = BTreeMap::new();
+    for (time_control, count) in time_controls {
+        let speed = Speed::classify(time_control.as_deref(), &thresholds);
+        *distribution.entry(speed).or_insert(0) += count;
+    }
+    Ok(distribution.into_iter().collect())
+}
+
+/// Counts the games of each speed, from fastest to slowest. Games without a
+/// TimeControl header are counted as `Unknown`.
+#[tauri::command]
+pub async fn get_speed_distribution(
+    file: PathBuf,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<(Speed, i64)>, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+    speed_distribution(db)
+}
+
+/// Counts the games played each year, oldest first, optionally only over the
+/// games of a player. Games without a valid year in their date are counted
+/// under "Unknown", last.
+#[tauri::command]
+pub async fn get_activity_by_year(
+    file: PathBuf,
+    id: Option<i32>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<(String, i64)>, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+    activity_by_year(db, id)
+}
+
+fn activity_by_year(
+    db: &mut SqliteConnection,
+    id: Option<i32>,
+) -> Result<Vec<(String, i64)>, Error> {
+    let mut query = games::table
+        .filter(games::deleted.eq(false))
+        .group_by(games::date)
+        .select((games::date, diesel::dsl::count_star()))
+        .into_boxed();
+    if let Some(id) = id {
+        query = query.filter(games::white_id.eq(id).or(games::black_id.eq(id)));
+    }
+    let dates: Vec<(Option<String>, i64)> = query.load(db)?;
+
+    let mut activity = BTreeMap::new();
+    for (date, count) in dates {
+        let year = date
+            .as_deref()
+            .and_then(|date| date.get(..4))
+            .filter(|year| year.bytes().all(|b| b.is_ascii_digit()))
+            .unwrap_or("Unknown");
+        *activity.entry(year.to_string()).or_insert(0) += count;
+    }
+    Ok(activity.into_iter().collect())
+}
+
+/// Changes the thresholds used to classify the games of a database by speed.
+/// Speeds are computed from the stored TimeControl headers whenever they are
+/// needed, so this applies to every game at once. Returns the new distribution.
+#[tauri::command]
+pub async fn reclassify_speeds(
+    file: PathBuf,
+    thresholds: SpeedThresholds,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<(Speed, i64)>, Error> {
+    if !thresholds.is_increasing() {
+        return Err(Error::InvalidSpeedThresholds);
+    }
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+    set_info(db, "SpeedThresholds", &serde_json::to_string(&thresholds)?)?;
+    speed_distribution(db)
+}
+
+fn matches_speed(
+    time_control: Option<&str>,
+    speed: Option<Speed>,
+    thresholds: &SpeedThresholds,
+) -> bool {
+    match speed {
+        Some(speed) => Speed::classify(time_control, thresholds) == speed,
+        None => true,
+    }
+}
+
+/// Computes the FIDE performance rating of a player, optionally only over the
+/// games of the given speed. Games against unrated opponents are ignored.
+#[tauri::command]
+pub async fn get_performance_rating(
+    file: PathBuf,
+    id: i32,
+    speed: Option<Speed>,
+    state: tauri::State<'_, AppState>,
+) -> Result<PerformanceRating, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+    let thresholds = get_speed_thresholds(db)?;
+
+    let games: Vec<(
+        i32,
+        Option<i32>,
+        Option<i32>,
+        Option<String>,
+        Option<String>,
+    )> = games::table
+        .select((
+            games::white_id,
+            games::white_elo,
+            games::black_elo,
+            games::result,
+            games::time_control,
+        ))
+        .filter(games::white_id.eq(id).or(games::black_id.eq(id)))
+        .filter(games::deleted.eq(false))
+        .load(db)?;
+
+    let results: Vec<(i32, f64)> = games
+        .into_iter()
+        .filter(|(_, _, _, _, time_control)| {
+            matches_speed(time_control.as_deref(), speed, &thresholds)
+        })
+        .filter_map(|(white_id, white_elo, black_elo, result, _)| {
+            let is_white = white_id == id;
+            let opponent_elo = if is_white { black_elo } else { white_elo }?;
+            let points = match (result.as_deref()?, is_white) {
+                ("1-0", true) | ("0-1", false) => 1.0,
+                ("1-0", false) | ("0-1", true) => 0.0,
+                ("1/2-1/2", _) => 0.5,
+                _ => return None,
+            };
+            Some((opponent_elo, points))
+        })
+        .collect();
+
+    Ok(PerformanceRating::from_results(&results))
+}
+
+/// Tallies the results of a player against opponents in each rating band of
+/// `bucket_size` points, keyed by the lowest rating of the band and sorted by
+/// it. Games against unrated opponents and unfinished games are ignored.
+#[tauri::command]
+pub async fn get_winrate_by_opponent_rating(
+    file: PathBuf,
+    id: i32,
+    bucket_size: i32,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<(i32, Results)>, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+    let bucket_size = bucket_size.max(1);
+
+    let games: Vec<(i32, Option<i32>, Option<i32>, Option<String>)> = games::table
+        .select((
+            games::white_id,
+            games::white_elo,
+            games::black_elo,
+            games::result,
+        ))
+        .filter(games::white_id.eq(id).or(games::black_id.eq(id)))
+        .filter(games::deleted.eq(false))
+        .load(db)?;
+
+    let mut buckets: BTreeMap<i32, Results> = BTreeMap::new();
+    for (white_id, white_elo, black_elo, result) in games {
+        let is_white = white_id == id;
+        let Some(opponent_elo) = (if is_white { black_elo } else { white_elo }) else {
+            continue;
+        };
+        let bucket = opponent_elo.div_euclid(bucket_size) * bucket_size;
+        match (result.as_deref(), is_white) {
+            (Some("1-0"), true) | (Some("0-1"), false) => {
+                buckets.entry(bucket).or_default().won += 1
+            }
+            (Some("1-0"), false) | (Some("0-1"), true) => {
+                buckets.entry(bucket).or_default().lost += 1
+            }
+            (Some("1/2-1/2"), _) => buckets.entry(bucket).or_default().draw += 1,
+            _ => {}
+        }
+    }
+    Ok(buckets.into_iter().collect())
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TimeStats {
+    pub speed: Speed,
+    pub games: usize,
+    /// Estimated total playing time in seconds. Clock times aren't stored, so
+    /// this only uses the speed of each game and its number of moves.
+    pub estimated_seconds: u64,
+    /// Estimated average game duration in seconds.
+    pub estimated_average_seconds: u64,
+}
+
+/// Estimates how long a player has spent playing, for each speed with a known
+/// time control. See [`Speed::estimated_duration`] for how games are timed.
+#[tauri::command]
+pub async fn get_player_time_stats(
+    file: PathBuf,
+    id: i32,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<TimeStats>, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+    let thresholds = get_speed_thresholds(db)?;
+
+    let games: Vec<(Option<String>, Option<i32>)> = games::table
+        .select((games::time_control, games::ply_count))
+        .filter(games::white_id.eq(id).or(games::black_id.eq(id)))
+        .filter(games::deleted.eq(false))
+        .load(db)?;
+
+    let mut totals: BTreeMap<Speed, (usize, u64)> = BTreeMap::new();
+    for (time_control, ply_count) in games {
+        let speed = Speed::classify(time_control.as_deref(), &thresholds);
+        let plies = ply_count.unwrap_or(0).max(0) as u64;
+        if let Some(duration) = speed.estimated_duration(plies) {
+            let total = totals.entry(speed).or_default();
+            total.0 += 1;
+            total.1 += duration;
+        }
+    }
+
+    Ok(totals
+        .into_iter()
+        .map(|(speed, (games, seconds))| TimeStats {
+            speed,
+            games,
+            estimated_seconds: seconds,
+            estimated_average_seconds: seconds / games as u64,
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RatingPoint {
+    pub date: String,
+    pub rating: i32,
+}
+
+/// Returns the rating of a player in each of their dated games, oldest first,
+/// optionally only over the games of the given speed.
+#[tauri::command]
+pub async fn get_rating_history(
+    file: PathBuf,
+    id: i32,
+    speed: Option<Speed>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<RatingPoint>, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+    let thresholds = get_speed_thresholds(db)?;
+
+    let games: Vec<(
+        i32,
+        Option<i32>,
+        Option<i32>,
+        Option<String>,
+        Option<String>,
+    )> = games::table
+        .select((
+            games::white_id,
+            games::white_elo,
+            games::black_elo,
+            games::date,
+            games::time_control,
+        ))
+        .filter(games::white_id.eq(id).or(games::black_id.eq(id)))
+        .filter(games::deleted.eq(false))
+        .order((games::date.asc(), games::time.asc()))
+        .load(db)?;
+
+    Ok(games
+        .into_iter()
+        .filter(|(_, _, _, _, time_control)| {
+            matches_speed(time_control.as_deref(), speed, &thresholds)
+        })
+        .filter_map(|(white_id, white_elo, black_elo, date, _)| {
+            let rating = if white_id == id { white_elo } else { black_elo }?;
+            Some(RatingPoint {
+                date: date?,
+                rating,
+            })
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RatingChangePoint {
+    pub date: String,
+    /// Rating gained or lost in this game.
+    pub change: i32,
+    /// Sum of the changes up to and including this game.
+    pub total: i32,
+}
+
+/// Returns the rating change of a player in each of their dated games, oldest
+/// first, along with the running total. Only games with a rating diff header,
+/// as in Lichess exports, are included.
+#[tauri::command]
+pub async fn get_rating_change_history(
+    file: PathBuf,
+    id: i32,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<RatingChangePoint>, Error> {
+    let db = &mut get_db_read_only(&state, file.to_str().unwrap())?;
+
+    let games: Vec<(i32, Option<i32>, Option<i32>, Option<String>)> = games::table
+        .select((
+            games::white_id,
+            games::white_rating_diff,
+            games::black_rating_diff,
+            games::date,
+        ))
+        .filter(games::white_id.eq(id).or(games::black_id.eq(id)))
+        .filter(games::deleted.eq(false))
+        .order((games::date.asc(), games::time.asc(), games::id.asc()))
+        .load(db)?;
+
+    let mut total = 0;
+    Ok(games
+        .into_iter()
+        .filter_map(|(white_id, white_diff, black_diff, date)| {
+            let change = if white_id == id {
+                white_diff
+            } else {
+                black_diff
+            }?;
+            total += change;
+            Some(RatingChangePoint {
+                date: date?,
+                change,
+                total,
+            })
+        })
+        .collect())
+}
+
+/// Inserts a game loaded from another database, matching its players, event
+/// and site by name.
+fn copy_game(
+    db: &mut SqliteConnection,
+    game: &Game,
+    white: &Player,
+    black: &Player,
+    event: &Event,
+    site: &Site,
+) -> Result<(), diesel::result::Error> {
+    let white_id = create_player(db, white.name.as_deref().unwrap_or("Unknown"))?.id;
+    let black_id = create_player(db, black.name.as_deref().unwrap_or("Unknown"))?.id;
+    let event_id = create_event(db, event.name.as_deref().unwrap_or("Unknown"))?.id;
+    let site_id = create_site(db, site.name.as_deref().unwrap_or("Unknown"))?.id;
+
+    let new_game = NewGame {
+        event_id,
+        site_id,
+        date: game.date.as_deref(),
+        time: game.time.as_deref(),
+        round: game.round.as_deref(),
+        white_id,
+        white_elo: game.white_elo,
+        black_id,
+        black_elo: game.black_elo,
+        white_material: game.white_material,
+        black_material: game.black_material,
+        result: game.result.as_deref(),
+        time_control: game.time_control.as_deref(),
+        eco: game.eco.as_deref(),
+        ply_count: game.ply_count.unwrap_or(game.moves.len() as i32),
+        fen: game.fen.as_deref(),
+        moves: &game.moves,
+        pawn_home: game.pawn_home,
+        opening: game.opening.as_deref(),
+        move_text: game.move_text.as_deref(),
+        variant: game.variant.as_deref(),
+        annotator: game.annotator.as_deref(),
+        source: game.source.as_deref(),
+        final_position_hash: game.final_position_hash,
+        is_puzzle: game.is_puzzle,
+        white_rating_diff: game.white_rating_diff,
+        black_rating_diff: game.black_rating_diff,
+        final_material: game.final_material.as_deref(),
+        starred: game.starred,
+    };
+    create_game(db, new_game)?;
+    Ok(())
+}
+
+/// Copies the games of each source database into `destination`, creating it
+/// if needed. Players, events and sites are matched by name. Returns the number
+/// of games copied.
+#[tauri::command]
+pub async fn merge_databases(
+    sources: Vec<PathBuf>,
+    destination: PathBuf,
+    skip_duplicates: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, Error> {
+    let db_exists = destination.exists();
+    let db = &mut get_db_or_create(
+        &state,
+        destination.to_str().unwrap(),
+        ConnectionOptions::default(),
+    )?;
+
+    if !db_exists {
+        let title = destination
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        create_database(db, &title, "")?;
+    }
+
+    let mut seen_games = if skip_duplicates.unwrap_or(false) {
+        Some(existing_game_keys(db)?)
+    } else {
+        None
+    };
+
+    let mut merged = 0;
+    for source in sources.iter().filter(|source| **source != destination) {
+        let source_db = &mut get_db_or_create(
+            &state,
+            source.to_str().unwrap(),
+            ConnectionOptions::default(),
+        )?;
+
+        let (white_players, black_players) = diesel::alias!(players as white, players as black);
+        let source_games = games::table
+            .inner_join(white_players.on(games::white_id.eq(white_players.field(players::id))))
+            .inner_join(black_players.on(games::black_id.eq(black_players.field(players::id))))
+            .inner_join(events::table.on(games::event_id.eq(events::id)))
+            .inner_join(sites::table.on(games::site_id.eq(sites::id)))
+            .filter(games::deleted.eq(false))
+            .load_iter::<(Game, Player, Player, Event, Site), DefaultLoadingMode>(source_db)?;
+
+        db.transaction::<_, Error, _>(|db| {
+            for row in source_games {
+                let (game, white, black, event, site) = row?;
+
+                if let Some(seen_games) = seen_games.as_mut() {
+                    let key = game_key(
+                        white.name.as_deref(),
+                        black.name.as_deref(),
+                        game.date.as_deref(),
+                        site.name.as_deref(),
+                        &game.moves,
+                    );
+                    if !seen_games.insert(key) {
+                        continue;
+                    }
+                }
+
+                copy_game(db, &game, &white, &black, &event, &site)?;
+                merged += 1;
+            }
+            Ok(())
+        })?;
+    }
+
+    update_info_counts(db)?;
+
+    Ok(merged)
+}
+
+/// What split_database groups the games of a database by.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum SplitKey {
+    Speed,
+    Year,
+    Result,
+}
+
+impl SplitKey {
+    /// Value of the key for a game, used in the name of the database it goes
+    /// to.
+    fn value(
+        self,
+        date: Option<&str>,
+        time_control: Option<&str>,
+        result: Option<&str>,
+        thresholds: &SpeedThresholds,
+    ) -> String {
+        match self {
+            SplitKey::Speed => format!("{:?}", Speed::classify(time_control, thresholds)),
+            SplitKey::Year => date
+                .and_then(|date| date.get(..4))
+                .filter(|year| year.bytes().all(|b| b.is_ascii_digit()))
+                .unwrap_or("Unknown")
+                .to_string(),
+            SplitKey::Result => match result {
+                Some("1-0") => "WhiteWins",
+                Some("0-1") => "BlackWins",
+                Some("1/2-1/2") => "Draws",
+                _ => "Unfinished",
+            }
+            .to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SplitPart {
+    /// Value of the split key shared by the games of this part.
+    pub value: String,
+    pub path: PathBuf,
+    pub games: usize,
+}
+
+/// Number of game ids looked up at once by split_database, well below the
+/// SQLite limit on bound parameters.
+const SPLIT_CHUNK_SIZE: usize = 1000;
+
+/// Copies the games of a database into a new database in `out_dir` for each
+/// value of `by`, named after the source file and the value, e.g.
+/// "games-Blitz.db3". The source database is left as is, and none of the new
+/// files may exist yet. Games in the trash aren't copied. If a part can't be
+/// written, the files created so far are removed.
+#[tauri::command]
+pub async fn split_database(
+    file: PathBuf,
+    by: SplitKey,
+    out_dir: PathBuf,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<SplitPart>, Error> {
+    let source_db = &mut get_db_read_only(&state, file.to_str().unwrap())?;
+    let thresholds = get_speed_thresholds(source_db)?;
+    let stem = file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let title = get_info(source_db, "Title")?.unwrap_or_else(|| stem.clone());
+
+    let games: Vec<(i32, Option<String>, Option<String>, Option<String>)> = games::table
+        .select((games::id, games::date, games::time_control, games::result))
+        .filter(games::deleted.eq(false))
+        .order(games::id.asc())
+        .load(source_db)?;
+    let mut groups: BTreeMap<String, Vec<i32>> = BTreeMap::new();
+    for (id, date, time_control, result) in games {
+        let value = by.value(
+            date.as_deref(),
+            time_control.as_deref(),
+            result.as_deref(),
+            &thresholds,
+        );
+        groups.entry(value).or_default().push(id);
+    }
+
+    let paths: Vec<PathBuf> = groups
+        .keys()
+        .map(|value| out_dir.join(format!("{stem}-{value}.db3")))
+        .collect();
+    if paths.iter().any(|path| path.exists()) {
+        return Err(Error::DatabaseExists);
+    }
+    if paths.iter().any(|path| path.to_str().is_none()) {
+        return Err(Error::InvalidPath);
+    }
+
+    let mut parts: Vec<SplitPart> = Vec::new();
+    for ((value, ids), path) in groups.into_iter().zip(paths) {
+        let path_str = path.to_str().unwrap();
+        let written = write_split_part(
+            &state,
+            source_db,
+            path_str,
+            &format!("{title} ({value})"),
+            &ids,
+        );
+        // Nothing else uses the new files, so don't keep a pool for each
+        close_db(&state, path_str);
+
+        if let Err(e) = written {
+            // Leave no part behind, so that the split can be run again
+            for created in parts
+                .iter()
+                .map(|part| part.path.as_path())
+                .chain([path.as_path()])
+            {
+                if !created.exists() {
+                    continue;
+                }
+                if let Err(e) = remove_db_files(&state, created.to_str().unwrap()) {
+                    log::warn!("Removing {}: {}", created.display(), e);
+                }
+            }
+            return Err(e);
+        }
+
+        parts.push(SplitPart {
+            value,
+            path,
+            games: ids.len(),
+        });
+    }
+
+    Ok(parts)
+}
+
+/// Creates the database of a part of split_database at `path`, with the games
+/// of the source database that have the given ids.
+fn write_split_part(
+    state: &State<AppState>,
+    source_db: &mut SqliteConnection,
+    path: &str,
+    title: &str,
+    ids: &[i32],
+) -> Result<(), Error> {
+    let db = &mut get_db_or_create(state, path, ConnectionOptions::default())?;
+    create_database(db, title, "")?;
+
+    let (white_players, black_players) = diesel::alias!(players as white, players as black);
+    db.transaction::<_, Error, _>(|db| {
+        for chunk in ids.chunks(SPLIT_CHUNK_SIZE) {
+            let rows: Vec<(Game, Player, Player, Event, Site)> = games::table
+                .inner_join(white_players.on(games::white_id.eq(white_players.field(players::id))))
+                .inner_join(black_players.on(games::black_id.eq(black_players.field(players::id))))
+                .inner_join(events::table.on(games::event_id.eq(events::id)))
+                .inner_join(sites::table.on(games::site_id.eq(sites::id)))
+                .filter(games::id.eq_any(chunk))
+                .order(games::id.asc())
+                .load(source_db)?;
+            for (game, white, black, event, site) in rows {
+                copy_game(db, &game, &white, &black, &event, &site)?;
+            }
+        }
+        Ok(())
+    })?;
+    db.batch_execute(INDEXES_SQL)?;
+    update_info_counts(db)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageReclaimed {
+    pub before: u64,
+    pub after: u64,
+}
+
+fn vacuum(
+    state: &AppState,
+    file: &Path,
+    db: &mut SqliteConnection,
+) -> Result<StorageReclaimed, Error> {
+    // VACUUM can't run while an import holds a transaction on the database
+    if state
+        .import_cancellations
+        .contains_key(file.to_string_lossy().as_ref())
+    {
+        return Err(Error::ImportInProgress);
+    }
+
+    let before = file.metadata()?.len();
+    db.batch_execute("VACUUM;")?;
+    let after = file.metadata()?.len();
+    Ok(StorageReclaimed { before, after })
+}
+
+/// Rebuilds the database file to reclaim the space left by deleted games.
+#[tauri::command]
+pub async fn vacuum_database(
+    file: PathBuf,
+    state: tauri::State<'_, AppState>,
+) -> Result<StorageReclaimed, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+    vacuum(&state, &file, db)
+}
+
+/// Deletes every game, player, event and site, keeping the database's title
+/// and description, then reclaims the space they used.
+#[tauri::command]
+pub async fn clear_database(
+    file: PathBuf,
+    state: tauri::State<'_, AppState>,
+) -> Result<StorageReclaimed, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+
+    db.transaction::<_, Error, _>(|db| {
+        diesel::delete(games::table).execute(db)?;
+        // Keep the "Unknown" placeholders that games without a name point to
+        diesel::delete(players::table.filter(players::id.ne(0))).execute(db)?;
+        diesel::delete(events::table.filter(events::id.ne(0))).execute(db)?;
+        diesel::delete(sites::table.filter(sites::id.ne(0))).execute(db)?;
+        update_info_counts(db)?;
+        Ok(())
+    })?;
+    state.line_cache.retain(|(_, path), _| path != &file);
+
+    vacuum(&state, &file, db)
+}
+
+#[tauri::command]
+pub async fn delete_database(
+    file: PathBuf,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), Error> {
+    remove_db_files(&state, file.to_str().unwrap())
+}
+
+/// Closes a database and deletes its file, along with the write-ahead log
+/// files left behind if it wasn't closed cleanly.
+fn remove_db_files(state: &State<AppState>, db_path: &str) -> Result<(), Error> {
+    close_db(state, db_path);
+
+    remove_file(db_path)?;
+    for suffix in ["-wal", "-shm"] {
+        let path = format!("{db_path}{suffix}");
+        if Path::new(&path).exists() {
+            remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_duplicated_games(
+    file: PathBuf,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+
+    db.batch_execute(
+        "
+        DELETE FROM Games
+        WHERE ID IN (
+            SELECT ID
+            FROM (
+                SELECT ID,
+                    ROW_NUMBER() OVER (PARTITION BY EventID, SiteID, Round, WhiteID, BlackID, Moves, Date, UTCTime ORDER BY ID) AS RowNum
+                FROM Games
+            ) AS Subquery
+            WHERE RowNum > 1
+        );
+        ",
+    )?;
+    touch_database(db)?;
+
+    Ok(())
+}
+
+/// Maximum number of rows returned by run_query.
+const MAX_QUERY_ROWS: usize = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryRows {
+    /// Rows as objects keyed by column name.
+    pub rows: Vec<serde_json::Value>,
+    /// Whether rows were left out because of the MAX_QUERY_ROWS limit.
+    pub truncated: bool,
+}
+
+/// Runs a single read-only statement, like a SELECT or a PRAGMA query, and
+/// returns up to MAX_QUERY_ROWS rows. The database is opened read-only, so
+/// nothing can be changed even if a statement slips through the checks.
+#[tauri::command]
+pub async fn run_query(file: PathBuf, sql: String) -> Result<QueryRows, Error> {
+    let conn =
+        rusqlite::Connection::open_with_flags(&file, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    query_rows(&conn, &sql)
+}
+
+fn query_rows(conn: &rusqlite::Connection, sql: &str) -> Result<QueryRows, Error> {
+    let keyword = sql
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    if !matches!(keyword.as_str(), "SELECT" | "WITH" | "PRAGMA") {
+        return Err(Error::InvalidQuery);
+    }
+
+    // A batch skips whitespace and comments, so anything after the first
+    // statement is another statement
+    let mut batch = rusqlite::Batch::new(conn, sql);
+    let Some(mut stmt) = batch.next()? else {
+        return Err(Error::InvalidQuery);
+    };
+    if batch.next()?.is_some() || !stmt.readonly() {
+        return Err(Error::InvalidQuery);
+    }
+
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let mut rows = stmt.query([])?;
+    let mut result = Vec::new();
+    let mut truncated = false;
+    while let Some(row) = rows.next()? {
+        if result.len() == MAX_QUERY_ROWS {
+            truncated = true;
+            break;
+        }
+        let mut object = serde_json::Map::new();
+        for (i, column) in columns.iter().enumerate() {
+            let value = match row.get_ref(i)? {
+                ValueRef::Null => serde_json::Value::Null,
+                ValueRef::Integer(n) => n.into(),
+                ValueRef::Real(x) => x.into(),
+                ValueRef::Text(text) => String::from_utf8_lossy(text).into(),
+                ValueRef::Blob(blob) => blob.to_vec().into(),
+            };
+            object.insert(column.clone(), value);
+        }
+        result.push(object.into());
+    }
+    Ok(QueryRows {
+        rows: result,
+        truncated,
+    })
+}
+
+#[derive(QueryableByName, Debug)]
+struct DuplicateGroup {
+    #[diesel(sql_type = Text)]
+    ids: String,
+}
+
+/// Finds the games that share the same players, date and moves, returning the
+/// ids of each group of duplicates, oldest first. Deleted games are ignored.
+#[tauri::command]
+pub async fn find_duplicate_games(
+    file: PathBuf,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<Vec<i32>>, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+
+    let groups: Vec<DuplicateGroup> = sql_query(
+        "SELECT GROUP_CONCAT(ID) AS ids
+        FROM (SELECT ID, WhiteID, BlackID, Date, Moves FROM Games WHERE NOT Deleted ORDER BY ID)
+        GROUP BY WhiteID, BlackID, Date, Moves
+        HAVING COUNT(*) > 1
+        ORDER BY MIN(ID);",
+    )
+    .load(db)?;
+
+    let groups = groups
+        .into_iter()
+        .map(|group| {
+            group
+                .ids
+                .split(',')
+                .map(str::parse)
+                .collect::<Result<Vec<i32>, _>>()
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(groups)
+}
+
+#[tauri::command]
+pub async fn delete_empty_games(
+    file: PathBuf,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+
+    diesel::delete(games::table.filter(games::ply_count.eq(0))).execute(db)?;
+    update_info_counts(db)?;
+
+    Ok(())
+}
+
+struct PgnGame {
     event: Option<String>,
     site: Option<String>,
     date: Option<String>,
@@ -1367,11 +5282,15 @@ struct PgnGame {
     result: Option<String>,
     time_control: Option<String>,
     eco: Option<String>,
+    opening: Option<String>,
     white_elo: Option<String>,
     black_elo: Option<String>,
     ply_count: Option<String>,
     fen: Option<String>,
+    variant: Option<String>,
+    annotator: Option<String>,
     moves: Option<Vec<String>>,
+    move_text: Option<String>,
 }
 
 impl PgnGame {
@@ -1379,24 +5298,28 @@ impl PgnGame {
         writeln!(
             writer,
             "[Event \"{}\"]",
-            self.event.as_deref().unwrap_or("")
+            self.event.as_deref().unwrap_or("?")
+        )?;
+        writeln!(writer, "[Site \"{}\"]", self.site.as_deref().unwrap_or("?"))?;
+        writeln!(
+            writer,
+            "[Date \"{}\"]",
+            self.date.as_deref().unwrap_or("????.??.??")
         )?;
-        writeln!(writer, "[Site \"{}\"]", self.site.as_deref().unwrap_or(""))?;
-        writeln!(writer, "[Date \"{}\"]", self.date.as_deref().unwrap_or(""))?;
         writeln!(
             writer,
             "[Round \"{}\"]",
-            self.round.as_deref().unwrap_or("")
+            self.round.as_deref().unwrap_or("?")
         )?;
         writeln!(
             writer,
             "[White \"{}\"]",
-            self.white.as_deref().unwrap_or("")
+            self.white.as_deref().unwrap_or("?")
         )?;
         writeln!(
             writer,
             "[Black \"{}\"]",
-            self.black.as_deref().unwrap_or("")
+            self.black.as_deref().unwrap_or("?")
         )?;
         writeln!(
             writer,
@@ -1409,6 +5332,9 @@ impl PgnGame {
         if let Some(eco) = self.eco.as_deref() {
             writeln!(writer, "[ECO \"{}\"]", eco)?;
         }
+        if let Some(opening) = self.opening.as_deref() {
+            writeln!(writer, "[Opening \"{}\"]", opening)?;
+        }
         if let Some(white_elo) = self.white_elo.as_deref() {
             writeln!(writer, "[WhiteElo \"{}\"]", white_elo)?;
         }
@@ -1418,16 +5344,28 @@ impl PgnGame {
         if let Some(ply_count) = self.ply_count.as_deref() {
             writeln!(writer, "[PlyCount \"{}\"]", ply_count)?;
         }
+        if let Some(annotator) = self.annotator.as_deref() {
+            writeln!(writer, "[Annotator \"{}\"]", annotator)?;
+        }
+        if let Some(variant) = self.variant.as_deref() {
+            if !is_standard_variant(Some(variant)) {
+                writeln!(writer, "[Variant \"{}\"]", variant)?;
+            }
+        }
         if let Some(fen) = self.fen.as_deref() {
             writeln!(writer, "[SetUp \"1\"]")?;
             writeln!(writer, "[FEN \"{}\"]", fen)?;
         }
         writeln!(writer)?;
-        for (i, move_) in self.moves.as_ref().unwrap().iter().enumerate() {
-            if i % 2 == 0 {
-                write!(writer, "{}. ", i / 2 + 1)?;
+        if let Some(move_text) = self.move_text.as_deref() {
+            write!(writer, "{} ", move_text)?;
+        } else {
+            for (i, move_) in self.moves.iter().flatten().enumerate() {
+                if i % 2 == 0 {
+                    write!(writer, "{}. ", i / 2 + 1)?;
+                }
+                write!(writer, "{} ", move_)?;
             }
-            write!(writer, "{} ", move_)?;
         }
         match self.result.as_deref() {
             Some("1-0") => writeln!(writer, "1-0"),
@@ -1447,21 +5385,65 @@ pub async fn export_to_pgn(
     state: tauri::State<'_, AppState>,
 ) -> Result<(), Error> {
     let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+    write_games_to_pgn(db, &dest_file, &GameQuery::default())
+}
+
+/// Exports the games matching `query` to a PGN file.
+#[tauri::command]
+pub async fn export_games(
+    file: PathBuf,
+    dest_file: PathBuf,
+    query: GameQuery,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+    write_games_to_pgn(db, &dest_file, &query)
+}
 
+/// Writes the games matching `query` to `dest_file`, compressing the output
+/// with gzip if the file has a `.gz` extension.
+fn write_games_to_pgn(
+    db: &mut SqliteConnection,
+    dest_file: &Path,
+    query: &GameQuery,
+) -> Result<(), Error> {
     let file = OpenOptions::new()
         .create(true)
         .write(true)
         .truncate(true)
         .open(dest_file)?;
 
-    let mut writer = BufWriter::new(file);
+    if dest_file.extension() == Some("gz".as_ref()) {
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        write_games(db, &mut encoder, query)?;
+        // Writes the gzip trailer, which dropping the encoder would do silently
+        encoder.finish()?;
+    } else {
+        write_games(db, file, query)?;
+    }
+    Ok(())
+}
+
+fn write_games(
+    db: &mut SqliteConnection,
+    writer: impl Write,
+    query: &GameQuery,
+) -> Result<(), Error> {
+    let mut writer = BufWriter::new(writer);
 
     let (white_players, black_players) = diesel::alias!(players as white, players as black);
-    games::table
+    let mut sql_query = games::table
         .inner_join(white_players.on(games::white_id.eq(white_players.field(players::id))))
         .inner_join(black_players.on(games::black_id.eq(black_players.field(players::id))))
         .inner_join(events::table.on(games::event_id.eq(events::id)))
         .inner_join(sites::table.on(games::site_id.eq(sites::id)))
+        .into_boxed();
+
+    if let Some(condition) = game_query_condition(query) {
+        sql_query = sql_query.filter(condition);
+    }
+
+    sql_query
         .load_iter::<(Game, Player, Player, Event, Site), DefaultLoadingMode>(db)?
         .flatten()
         .map(|(game, white, black, event, site)| {
@@ -1475,10 +5457,14 @@ pub async fn export_to_pgn(
                 result: game.result,
                 time_control: game.time_control,
                 eco: game.eco,
+                opening: game.opening,
                 white_elo: game.white_elo.map(|e| e.to_string()),
                 black_elo: game.black_elo.map(|e| e.to_string()),
                 ply_count: game.ply_count.map(|e| e.to_string()),
                 fen: game.fen.clone(),
+                variant: game.variant,
+                annotator: game.annotator,
+                move_text: game.move_text,
                 moves: decode_moves(
                     game.moves,
                     if let Some(fen) = game.fen {
@@ -1492,68 +5478,353 @@ pub async fn export_to_pgn(
 
             pgn.write(&mut writer)?;
 
-            Ok(())
-        })
-        .collect::<Result<Vec<_>, Error>>()?;
+            Ok(())
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Moves a game to the trash, from where it can be restored with restore_game
+/// until purge_deleted runs, and updates the database counts. With
+/// `permanent`, the game is deleted right away; players left without any games
+/// are then kept unless `delete_orphaned_players` is set.
+#[tauri::command]
+pub async fn delete_db_game(
+    file: PathBuf,
+    game_id: i32,
+    permanent: Option<bool>,
+    delete_orphaned_players: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+
+    if !permanent.unwrap_or(false) {
+        diesel::update(games::table.filter(games::id.eq(game_id)))
+            .set(games::deleted.eq(true))
+            .execute(db)?;
+        update_info_counts(db)?;
+        clear_search_caches(&state, &file);
+        return Ok(());
+    }
+
+    db.transaction::<_, Error, _>(|db| {
+        let game_players: Option<(i32, i32)> = games::table
+            .filter(games::id.eq(game_id))
+            .select((games::white_id, games::black_id))
+            .first(db)
+            .optional()?;
+        let Some((white_id, black_id)) = game_players else {
+            return Ok(());
+        };
+
+        diesel::delete(games::table.filter(games::id.eq(game_id))).execute(db)?;
+
+        if delete_orphaned_players.unwrap_or(false) {
+            delete_player_if_orphaned(db, white_id)?;
+            delete_player_if_orphaned(db, black_id)?;
+        }
+
+        update_info_counts(db)?;
+        Ok(())
+    })?;
+    clear_search_caches(&state, &file);
+
+    Ok(())
+}
+
+/// Deletes the games matching the filters of `query` and updates the database
+/// counts, returning the number of games deleted. A query without any filter
+/// deletes nothing; use clear_database to empty a database. Position filters
+/// are only applied by search_position, so they are rejected.
+#[tauri::command]
+pub async fn delete_games(
+    file: PathBuf,
+    query: GameQuery,
+    delete_orphaned_players: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, Error> {
+    if query.position.is_some() {
+        return Err(Error::PositionFilterUnsupported);
+    }
+
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+
+    let filters = GameQuery {
+        include_deleted: true,
+        ..query.clone()
+    };
+    if game_query_condition::<games::table>(&filters).is_none() {
+        return Ok(0);
+    }
+
+    let deleted = db.transaction::<_, Error, _>(|db| {
+        let players_query = games::table
+            .select((games::white_id, games::black_id))
+            .into_boxed();
+        let players: HashSet<i32> = apply_filters(players_query, &query)
+            .load::<(i32, i32)>(db)?
+            .into_iter()
+            .flat_map(|(white_id, black_id)| [white_id, black_id])
+            .collect();
+
+        let ids = apply_filters(games::table.select(games::id).into_boxed(), &query);
+        let deleted = diesel::delete(games::table.filter(games::id.eq_any(ids))).execute(db)?;
+
+        if delete_orphaned_players.unwrap_or(false) {
+            for player_id in players {
+                delete_player_if_orphaned(db, player_id)?;
+            }
+        }
+
+        update_info_counts(db)?;
+        Ok(deleted)
+    })?;
+    clear_search_caches(&state, &file);
+
+    Ok(deleted)
+}
+
+/// Takes a game out of the trash and updates the database counts. Returns
+/// false if there is no such game.
+#[tauri::command]
+pub async fn restore_game(
+    file: PathBuf,
+    id: i32,
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+
+    let restored = diesel::update(games::table.filter(games::id.eq(id)))
+        .set(games::deleted.eq(false))
+        .execute(db)?;
+    if restored > 0 {
+        update_info_counts(db)?;
+        clear_search_caches(&state, &file);
+    }
+    Ok(restored > 0)
+}
+
+/// Permanently deletes the games in the trash and updates the database counts,
+/// returning the number of games deleted.
+#[tauri::command]
+pub async fn purge_deleted(
+    file: PathBuf,
+    delete_orphaned_players: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+
+    let purged = db.transaction::<_, Error, _>(|db| {
+        let players: HashSet<i32> = games::table
+            .filter(games::deleted.eq(true))
+            .select((games::white_id, games::black_id))
+            .load::<(i32, i32)>(db)?
+            .into_iter()
+            .flat_map(|(white_id, black_id)| [white_id, black_id])
+            .collect();
+
+        let purged = diesel::delete(games::table.filter(games::deleted.eq(true))).execute(db)?;
+
+        if delete_orphaned_players.unwrap_or(false) {
+            for player_id in players {
+                delete_player_if_orphaned(db, player_id)?;
+            }
+        }
+
+        update_info_counts(db)?;
+        Ok(purged)
+    })?;
+    clear_search_caches(&state, &file);
+
+    Ok(purged)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn merge_players(
+    file: PathBuf,
+    player1: i32,
+    player2: i32,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), Error> {
+    // Merging a player into itself would delete it along with nothing else
+    if player1 == player2 {
+        return Err(Error::NotDistinctPlayers);
+    }
+
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+
+    // Check if the players never played against each other
+    let count: i64 = games::table
+        .filter(games::white_id.eq(player1).and(games::black_id.eq(player2)))
+        .or_filter(games::white_id.eq(player2).and(games::black_id.eq(player1)))
+        .limit(1)
+        .count()
+        .get_result(db)?;
+
+    if count > 0 {
+        return Err(Error::NotDistinctPlayers);
+    }
+
+    // Game counts are computed from the games, so moving them is enough
+    db.transaction::<_, Error, _>(|db| {
+        diesel::update(games::table.filter(games::white_id.eq(player1)))
+            .set(games::white_id.eq(player2))
+            .execute(db)?;
+        diesel::update(games::table.filter(games::black_id.eq(player1)))
+            .set(games::black_id.eq(player2))
+            .execute(db)?;
+
+        diesel::delete(players::table.filter(players::id.eq(player1))).execute(db)?;
+
+        let player_count: i64 = players::table.count().get_result(db)?;
+        set_info(db, "PlayerCount", &player_count.to_string())?;
+        touch_database(db)?;
+        Ok(())
+    })?;
+    // Cached games for position searches refer to players by id
+    clear_search_caches(&state, &file);
+
     Ok(())
 }
 
+/// Stars a game, or unstars it if it was starred, and returns whether it is
+/// now starred, or `None` if there is no such game. Starred games can be
+/// listed with the starred filter of get_games.
 #[tauri::command]
-pub async fn delete_db_game(
+pub async fn toggle_star(
+    file: PathBuf,
+    id: i32,
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<bool>, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+
+    let starred = db.transaction::<_, diesel::result::Error, _>(|db| {
+        diesel::update(games::table.filter(games::id.eq(id)))
+            .set(games::starred.eq(not(games::starred)))
+            .execute(db)?;
+        let starred = games::table
+            .filter(games::id.eq(id))
+            .select(games::starred)
+            .first(db)
+            .optional()?;
+        if starred.is_some() {
+            touch_database(db)?;
+        }
+        Ok(starred)
+    })?;
+    Ok(starred)
+}
+
+/// Tags a game, e.g. "to review" or "endgame study", so it can be found with
+/// the tag filter of get_games. Tagging a game twice with the same tag does
+/// nothing.
+#[tauri::command]
+pub async fn add_tag(
     file: PathBuf,
     game_id: i32,
+    tag: String,
     state: tauri::State<'_, AppState>,
 ) -> Result<(), Error> {
     let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err(Error::EmptyTag);
+    }
 
-    diesel::delete(games::table.filter(games::id.eq(game_id))).execute(db)?;
-
+    let added = diesel::insert_or_ignore_into(game_tags::table)
+        .values((game_tags::game_id.eq(game_id), game_tags::tag.eq(tag)))
+        .execute(db)?;
+    if added > 0 {
+        touch_database(db)?;
+    }
     Ok(())
 }
 
+/// Removes a tag from a game, returning whether the game had it.
 #[tauri::command]
-#[specta::specta]
-pub async fn merge_players(
+pub async fn remove_tag(
     file: PathBuf,
-    player1: i32,
-    player2: i32,
+    game_id: i32,
+    tag: String,
     state: tauri::State<'_, AppState>,
-) -> Result<(), Error> {
+) -> Result<bool, Error> {
     let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
 
-    // Check if the players never played against each other
-    let count: i64 = games::table
-        .filter(games::white_id.eq(player1).and(games::black_id.eq(player2)))
-        .or_filter(games::white_id.eq(player2).and(games::black_id.eq(player1)))
-        .limit(1)
-        .count()
-        .get_result(db)?;
-
-    if count > 0 {
-        return Err(Error::NotDistinctPlayers);
+    let removed = diesel::delete(
+        game_tags::table
+            .filter(game_tags::game_id.eq(game_id))
+            .filter(game_tags::tag.eq(tag.trim())),
+    )
+    .execute(db)?;
+    if removed > 0 {
+        touch_database(db)?;
     }
+    Ok(removed > 0)
+}
 
-    diesel::update(games::table.filter(games::white_id.eq(player1)))
-        .set(games::white_id.eq(player2))
-        .execute(db)?;
-    diesel::update(games::table.filter(games::black_id.eq(player1)))
-        .set(games::black_id.eq(player2))
-        .execute(db)?;
+/// Returns the tags of a game, sorted alphabetically.
+#[tauri::command]
+pub async fn get_tags(
+    file: PathBuf,
+    game_id: i32,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<String>, Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
 
-    diesel::delete(players::table.filter(players::id.eq(player1))).execute(db)?;
+    let tags = game_tags::table
+        .filter(game_tags::game_id.eq(game_id))
+        .select(game_tags::tag)
+        .order(game_tags::tag.asc())
+        .load(db)?;
+    Ok(tags)
+}
 
-    let player_count: i64 = players::table.count().get_result(db)?;
-    diesel::insert_into(info::table)
-        .values((
-            info::name.eq("PlayerCount"),
-            info::value.eq(player_count.to_string()),
-        ))
-        .on_conflict(info::name)
-        .do_update()
-        .set(info::value.eq(player_count.to_string()))
-        .execute(db)?;
+/// Renames a player. Fails if another player already has the new name, in
+/// which case the two can be merged with merge_players instead.
+#[tauri::command]
+pub async fn rename_player(
+    file: PathBuf,
+    id: i32,
+    new_name: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), Error> {
+    let db = &mut get_db_or_create(&state, file.to_str().unwrap(), ConnectionOptions::default())?;
+    set_player_name(db, id, &new_name)
+}
 
-    Ok(())
+fn set_player_name(db: &mut SqliteConnection, id: i32, new_name: &str) -> Result<(), Error> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() {
+        return Err(Error::EmptyPlayerName);
+    }
+
+    db.transaction::<_, Error, _>(|db| {
+        let taken = players::table
+            .filter(players::name.eq(new_name))
+            .filter(players::id.ne(id))
+            .select(players::id)
+            .first::<i32>(db)
+            .optional()?
+            .is_some();
+        if taken {
+            return Err(Error::PlayerNameTaken);
+        }
+
+        let renamed = diesel::update(players::table.filter(players::id.eq(id)))
+            .set((
+                players::name.eq(new_name),
+                players::normalized_name.eq(normalize_name(new_name)),
+            ))
+            .execute(db)?;
+        if renamed == 0 {
+            return Err(Error::PlayerNotFound);
+        }
+        touch_database(db)?;
+        Ok(())
+    })
 }
 
 #[tauri::command]
@@ -1562,6 +5833,13 @@ pub fn clear_games(state: tauri::State<'_, AppState>) {
     state.clear();
 }
 
+/// Drops the games loaded for position searches and the cached search results
+/// for `file`, which are stale once its games change.
+fn clear_search_caches(state: &AppState, file: &Path) {
+    state.db_cache.lock().unwrap().clear();
+    state.line_cache.retain(|(_, path), _| path != file);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1581,4 +5859,929 @@ mod tests {
         let pawn_home = get_pawn_home(&Board::from_ascii_board_fen(b"8/8/8/8/8/8/8/8").unwrap());
         assert_eq!(pawn_home, 0b0000000000000000);
     }
+
+    #[test]
+    fn head_to_head() {
+        let games = vec![
+            (1, Some("1-0".to_string())),
+            (1, Some("1/2-1/2".to_string())),
+            (2, Some("1-0".to_string())),
+            (2, Some("0-1".to_string())),
+            (2, Some("*".to_string())),
+        ];
+        let stats = HeadToHead::from_games(1, &games);
+        assert_eq!(stats.player1_wins, 2);
+        assert_eq!(stats.player2_wins, 1);
+        assert_eq!(stats.draws, 1);
+        assert_eq!(
+            stats.player1_white,
+            Results {
+                won: 1,
+                lost: 0,
+                draw: 1
+            }
+        );
+        assert_eq!(
+            stats.player1_black,
+            Results {
+                won: 1,
+                lost: 1,
+                draw: 0
+            }
+        );
+
+        assert_eq!(HeadToHead::from_games(1, &[]), HeadToHead::default());
+    }
+
+    #[test]
+    fn performance_rating() {
+        let performance = PerformanceRating::from_results(&[(2000, 1.0), (2100, 0.5)]);
+        assert_eq!(performance.average_opponent, Some(2050));
+        assert_eq!(performance.performance, Some(2050 + 193));
+        assert_eq!(performance.games, 2);
+
+        let performance = PerformanceRating::from_results(&[(1500, 0.0)]);
+        assert_eq!(performance.performance, Some(700));
+
+        assert_eq!(
+            PerformanceRating::from_results(&[]),
+            PerformanceRating::default()
+        );
+    }
+
+    #[test]
+    fn material_signatures() {
+        let position: Chess = Fen::from_ascii(b"8/8/4k3/8/3KP3/8/8/R6r w - - 0 1")
+            .unwrap()
+            .into_position(CastlingMode::Standard)
+            .unwrap();
+        assert_eq!(material_signature(position.board()), "KRPvKR");
+        assert_eq!(
+            material_signature(Chess::default().board()),
+            "KQRRBBNNPPPPPPPPvKQRRBBNNPPPPPPPP"
+        );
+        assert_eq!(mirror_material("KRPvKR"), "KRvKRP");
+    }
+
+    #[test]
+    fn split_key_values() {
+        let thresholds = SpeedThresholds::default();
+        let value = |key: SplitKey, date, time_control, result| {
+            key.value(date, time_control, result, &thresholds)
+        };
+        assert_eq!(value(SplitKey::Speed, None, Some("180+2"), None), "Blitz");
+        assert_eq!(value(SplitKey::Speed, None, None, None), "Unknown");
+        assert_eq!(
+            value(SplitKey::Year, Some("2023.05.01"), None, None),
+            "2023"
+        );
+        assert_eq!(
+            value(SplitKey::Year, Some("????.??.??"), None, None),
+            "Unknown"
+        );
+        assert_eq!(
+            value(SplitKey::Result, None, None, Some("1/2-1/2")),
+            "Draws"
+        );
+        assert_eq!(value(SplitKey::Result, None, None, None), "Unfinished");
+    }
+
+    #[test]
+    fn like_patterns() {
+        assert_eq!(escape_like("B2"), "B2");
+        assert_eq!(escape_like("100%_\\"), "100\\%\\_\\\\");
+
+        let mut db = SqliteConnection::establish(":memory:").unwrap();
+        let matches = |db: &mut SqliteConnection, text: &str, pattern: &str| -> bool {
+            diesel::select(
+                text.into_sql::<Text>()
+                    .like(format!("%{}%", escape_like(pattern)))
+                    .escape('\\'),
+            )
+            .get_result(db)
+            .unwrap()
+        };
+        assert!(matches(&mut db, "a_b", "a_b"));
+        assert!(!matches(&mut db, "axb", "a_b"));
+        assert!(!matches(&mut db, "abc", "%"));
+        assert!(matches(&mut db, "50% off", "50%"));
+    }
+
+    #[test]
+    fn results_score() {
+        let results = Results {
+            won: 1,
+            lost: 2,
+            draw: 1,
+        };
+        assert_eq!(results.score(), Some(0.375));
+        assert_eq!(Results::default().score(), None);
+    }
+
+    #[test]
+    fn decompress_formats() {
+        use std::io::Cursor;
+
+        const PGN: &str = "[White \"A\"]\n[Black \"B\"]\n[Result \"1-0\"]\n\n1. e4 e5 1-0\n\n\
+                           [White \"C\"]\n[Black \"D\"]\n[Result \"0-1\"]\n\n1. d4 d5 0-1\n";
+
+        fn count_games(reader: Box<dyn Read + Send>) -> usize {
+            read_games(reader, &mut Importer::new(None, ImportOptions::default()))
+                .flatten()
+                .count()
+        }
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(PGN.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(PGN.as_bytes()).unwrap();
+        let xz_compressed = encoder.finish().unwrap();
+
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        encoder.write_all(PGN.as_bytes()).unwrap();
+        let bz2_compressed = encoder.finish().unwrap();
+
+        let zst_compressed = zstd::encode_all(PGN.as_bytes(), 0).unwrap();
+
+        let plain = decompress(Cursor::new(PGN.as_bytes()), Some("pgn".as_ref())).unwrap();
+        let gzip = decompress(Cursor::new(compressed), Some("gz".as_ref())).unwrap();
+        let xz = decompress(Cursor::new(xz_compressed), Some("xz".as_ref())).unwrap();
+        let bz2 = decompress(Cursor::new(bz2_compressed), Some("bz2".as_ref())).unwrap();
+        let zst = decompress(Cursor::new(zst_compressed), Some("zst".as_ref())).unwrap();
+        assert_eq!(count_games(plain), 2);
+        assert_eq!(count_games(gzip), 2);
+        assert_eq!(count_games(xz), 2);
+        assert_eq!(count_games(bz2), 2);
+        assert_eq!(count_games(zst), 2);
+    }
+
+    #[test]
+    fn read_only_uris() {
+        assert_eq!(
+            read_only_uri("/data/db/games.db3"),
+            "file:/data/db/games.db3?mode=ro"
+        );
+        assert_eq!(
+            read_only_uri("C:\\Users\\me\\db\\50%.db3"),
+            "file:/C:/Users/me/db/50%25.db3?mode=ro"
+        );
+        assert_eq!(
+            read_only_uri("/tmp/what?.db3"),
+            "file:/tmp/what%3f.db3?mode=ro"
+        );
+    }
+
+    #[test]
+    fn pgn_file_names() {
+        use std::io::Cursor;
+
+        const PGN: &str = "[White \"A\"]\n[Black \"B\"]\n[Result \"1-0\"]\n\n1. e4 e5 1-0\n";
+
+        for (name, title) in [
+            ("games", "games"),
+            ("games.pgn", "games"),
+            (".hidden", "hidden"),
+        ] {
+            let path = Path::new(name);
+            let reader = decompress(Cursor::new(PGN.as_bytes()), path.extension()).unwrap();
+            let mut importer = Importer::new(None, ImportOptions::default());
+            assert_eq!(read_games(reader, &mut importer).flatten().count(), 1);
+            assert_eq!(default_title(path), title);
+        }
+        assert_eq!(default_title(Path::new("games.pgn.zst")), "games");
+        assert_eq!(default_title(Path::new("...")), "Untitled");
+    }
+
+    #[test]
+    fn normalize_dates() {
+        assert_eq!(normalize_date("2023.1.5"), "2023.01.05");
+        assert_eq!(normalize_date("2023-01-05"), "2023.01.05");
+        assert_eq!(normalize_date("2023.??.??"), "2023.??.??");
+        assert_eq!(normalize_date("2023.?.?"), "2023.??.??");
+        assert_eq!(normalize_date("????.??.??"), "????.??.??");
+        assert_eq!(normalize_date("yesterday"), "yesterday");
+    }
+
+    #[test]
+    fn read_games_ratings() {
+        use std::io::Cursor;
+
+        const PGN: &str = "[White \"A\"]\n[Black \"B\"]\n[WhiteElo \"1500\"]\n\n1. e4 e5 1-0\n";
+
+        let mut importer = Importer::new(None, ImportOptions::default());
+        let games: Vec<_> = read_games(Cursor::new(PGN), &mut importer)
+            .flatten()
+            .collect();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].white_elo, Some(1500));
+        assert_eq!(games[0].black_elo, None);
+
+        let options = ImportOptions {
+            require_ratings: true,
+            ..Default::default()
+        };
+        let mut importer = Importer::new(None, options);
+        assert_eq!(read_games(Cursor::new(PGN), &mut importer).count(), 0);
+        assert_eq!(importer.stats.total_games, 1);
+        assert_eq!(importer.stats.unrated_games, 1);
+
+        const UNRATED: &str = "[WhiteElo \"?\"]\n[BlackElo \"-\"]\n\n1. e4 e5 1-0\n\n\
+                               [WhiteElo \"2500k\"]\n[BlackElo \"1500\"]\n\n1. e4 e5 1-0\n";
+        let mut importer = Importer::new(None, ImportOptions::default());
+        let games: Vec<_> = read_games(Cursor::new(UNRATED), &mut importer)
+            .flatten()
+            .collect();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].white_elo, None);
+        assert_eq!(games[0].black_elo, None);
+        assert_eq!(importer.stats.invalid_games, 1);
+    }
+
+    #[test]
+    fn read_games_times() {
+        use std::io::Cursor;
+
+        const PGN: &str = "[EndTime \"10:05:00\"]\n[StartTime \"10:00:00\"]\n\n1. e4 e5 1-0\n\n\
+                           [UTCTime \"09:00:00\"]\n[StartTime \"10:00:00\"]\n\n1. e4 e5 1-0\n\n\
+                           [EndTime \"10:05:00\"]\n\n1. e4 e5 1-0\n";
+
+        let mut importer = Importer::new(None, ImportOptions::default());
+        let times: Vec<_> = read_games(Cursor::new(PGN), &mut importer)
+            .flatten()
+            .map(|game| game.time)
+            .collect();
+        assert_eq!(
+            times,
+            [
+                Some("10:00:00".to_string()),
+                Some("09:00:00".to_string()),
+                Some("10:05:00".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn import_games_resume() {
+        const PGN: &str = "[White \"A\"]\n[Black \"B\"]\n[Result \"*\"]\n\n1. e4 e5 *\n\n\
+                           [White \"C\"]\n[Black \"D\"]\n[Result \"0-1\"]\n\n1. d4 d5 0-1\n\n\
+                           [White \"E\"]\n[Black \"F\"]\n[Result \"1-0\"]\n\n1. c4 c5 1-0\n\n\
+                           [White \"G\"]\n[Black \"H\"]\n[Result \"*\"]\n\n1. e4 e5 *\n";
+
+        let mut db = SqliteConnection::establish(":memory:").unwrap();
+        db.batch_execute(CREATE_TABLES_SQL).unwrap();
+        let checkpoint = ImportCheckpoint {
+            file: "games.pgn".to_string(),
+            size: PGN.len() as u64,
+            games: 2,
+        };
+        let options = ImportOptions {
+            skip_unfinished: true,
+            ..Default::default()
+        };
+        let stats = import_games(
+            &mut db,
+            PGN.as_bytes(),
+            None,
+            options,
+            Some(checkpoint.clone()),
+            &AtomicBool::new(false),
+            |_| {},
+        )
+        .unwrap();
+        // The unfinished first game was read before the interruption, so it
+        // is resumed rather than skipped again
+        assert_eq!(stats.total_games, 4);
+        assert_eq!(stats.resumed_games, 2);
+        assert_eq!(stats.unfinished_games, 1);
+        assert_eq!(stats.imported_games, 1);
+
+        let saved = get_info(&mut db, IMPORT_CHECKPOINT).unwrap().unwrap();
+        assert_eq!(
+            serde_json::from_str::<ImportCheckpoint>(&saved).unwrap(),
+            ImportCheckpoint {
+                games: 3,
+                ..checkpoint
+            }
+        );
+    }
+
+    #[test]
+    fn import_games_batch_sizes() {
+        let pgn: String = (0..120)
+            .map(|i| {
+                format!(
+                    "[White \"W{}\"]\n[Black \"B{}\"]\n\n1. e4 e5 {}\n\n",
+                    i % 7,
+                    i % 11,
+                    if i % 3 == 0 { "1-0" } else { "1/2-1/2" }
+                )
+            })
+            .collect();
+
+        let counts: Vec<(usize, i64, i64)> = [1, 50, 5000]
+            .into_iter()
+            .map(|batch_size| {
+                let mut db = SqliteConnection::establish(":memory:").unwrap();
+                db.batch_execute(CREATE_TABLES_SQL).unwrap();
+                let options = ImportOptions {
+                    batch_size: Some(batch_size),
+                    ..Default::default()
+                };
+                let stats = import_games(
+                    &mut db,
+                    pgn.as_bytes(),
+                    None,
+                    options,
+                    None,
+                    &AtomicBool::new(false),
+                    |_| {},
+                )
+                .unwrap();
+                let games: i64 = games::table.count().get_result(&mut db).unwrap();
+                let players: i64 = players::table.count().get_result(&mut db).unwrap();
+                (stats.imported_games, games, players)
+            })
+            .collect();
+        assert_eq!(counts[0], (120, 120, 19));
+        assert!(counts.iter().all(|&count| count == counts[0]));
+    }
+
+    #[test]
+    fn move_tree_continuations() {
+        const PGN: &str = "[White \"A\"]\n[Result \"1-0\"]\n\n1. e4 e5 1-0\n\n\
+                           [White \"B\"]\n[Result \"0-1\"]\n\n1. e4 c5 2. Nf3 0-1\n\n\
+                           [White \"C\"]\n[Result \"1/2-1/2\"]\n\n1. e4 c5 1/2-1/2\n\n\
+                           [White \"D\"]\n[Result \"*\"]\n\n1. d4 d5 *\n\n\
+                           [Variant \"Crazyhouse\"]\n[Result \"1-0\"]\n\n1. e4 e5 1-0\n";
+
+        let mut db = SqliteConnection::establish(":memory:").unwrap();
+        create_database(&mut db, "Test", "").unwrap();
+        import_games(
+            &mut db,
+            PGN.as_bytes(),
+            None,
+            ImportOptions::default(),
+            None,
+            &AtomicBool::new(false),
+            |_| {},
+        )
+        .unwrap();
+
+        let entry = |san: &str, games, white_wins, black_wins, draws| MoveTreeEntry {
+            san: san.to_string(),
+            games,
+            white_wins,
+            black_wins,
+            draws,
+        };
+        let mut root = move_tree(&mut db, &[], None).unwrap();
+        root.sort_by(|a, b| a.san.cmp(&b.san));
+        assert_eq!(root, vec![entry("d4", 1, 0, 0, 0), entry("e4", 3, 1, 1, 1)]);
+
+        let mut e4 = move_tree(&mut db, &["e4".to_string()], None).unwrap();
+        e4.sort_by(|a, b| a.san.cmp(&b.san));
+        assert_eq!(e4, vec![entry("c5", 2, 0, 1, 1), entry("e5", 1, 1, 0, 0)]);
+
+        let too_long = vec!["Nf3".to_string(); MAX_MOVE_TREE_PLIES + 1];
+        assert!(matches!(
+            move_tree(&mut db, &too_long, None),
+            Err(Error::LineTooLong(_))
+        ));
+    }
+
+    #[test]
+    fn players_game_info_results() {
+        const PGN: &str = "[White \"A\"]\n[Black \"B\"]\n[Result \"1-0\"]\n\n1. e4 e5 1-0\n\n\
+                           [White \"B\"]\n[Black \"A\"]\n[Result \"1/2-1/2\"]\n\n1. e4 e5 1/2-1/2\n\n\
+                           [White \"B\"]\n[Black \"A\"]\n[Result \"0-1\"]\n\n1. e4 e5 0-1\n\n\
+                           [White \"C\"]\n[Black \"A\"]\n[Result \"*\"]\n\n1. e4 e5 *\n";
+
+        let mut db = SqliteConnection::establish(":memory:").unwrap();
+        create_database(&mut db, "Test", "").unwrap();
+        import_games(
+            &mut db,
+            PGN.as_bytes(),
+            None,
+            ImportOptions::default(),
+            None,
+            &AtomicBool::new(false),
+            |_| {},
+        )
+        .unwrap();
+        let mut id = |name: &str| -> i32 {
+            players::table
+                .filter(players::name.eq(name))
+                .select(players::id)
+                .first(&mut db)
+                .unwrap()
+        };
+        let (a, b, c) = (id("A"), id("B"), id("C"));
+        let no_games = 999;
+
+        let infos = players_game_info_bulk(&mut db, &[a, b, c, no_games]).unwrap();
+        let results = |won, lost, draw| Results { won, lost, draw };
+        let summary = |id: i32| {
+            let info = &infos[&id];
+            (
+                (info.won, info.lost, info.draw),
+                info.white_results.clone(),
+                info.black_results.clone(),
+            )
+        };
+        assert_eq!(summary(a), ((2, 0, 1), results(1, 0, 0), results(1, 0, 1)));
+        assert_eq!(summary(b), ((0, 2, 1), results(0, 1, 1), results(0, 1, 0)));
+        assert_eq!(summary(c), ((0, 0, 0), results(0, 0, 0), results(0, 0, 0)));
+        assert_eq!(
+            summary(no_games),
+            ((0, 0, 0), results(0, 0, 0), results(0, 0, 0))
+        );
+    }
+
+    #[test]
+    fn activity_years() {
+        const PGN: &str = "[White \"A\"]\n[Date \"2021.03.03\"]\n\n1. e4 e5 1-0\n\n\
+                           [White \"A\"]\n[Date \"2020.01.01\"]\n\n1. e4 e5 1-0\n\n\
+                           [White \"B\"]\n[Date \"2020.05.02\"]\n\n1. e4 e5 1-0\n\n\
+                           [White \"A\"]\n[Date \"????.??.??\"]\n\n1. e4 e5 1-0\n";
+
+        let mut db = SqliteConnection::establish(":memory:").unwrap();
+        create_database(&mut db, "Test", "").unwrap();
+        import_games(
+            &mut db,
+            PGN.as_bytes(),
+            None,
+            ImportOptions::default(),
+            None,
+            &AtomicBool::new(false),
+            |_| {},
+        )
+        .unwrap();
+        let player_b: i32 = players::table
+            .filter(players::name.eq("B"))
+            .select(players::id)
+            .first(&mut db)
+            .unwrap();
+
+        let year = |year: &str, count| (year.to_string(), count);
+        assert_eq!(
+            activity_by_year(&mut db, None).unwrap(),
+            vec![year("2020", 2), year("2021", 1), year("Unknown", 1)]
+        );
+        assert_eq!(
+            activity_by_year(&mut db, Some(player_b)).unwrap(),
+            vec![year("2020", 1)]
+        );
+    }
+
+    #[test]
+    fn trashed_games_not_counted() {
+        const PGN: &str = "[White \"A\"]\n[Black \"B\"]\n[Result \"1-0\"]\n[ECO \"C20\"]\n\n\
+                           1. e4 e5 1-0\n\n\
+                           [White \"A\"]\n[Black \"B\"]\n[Result \"0-1\"]\n[ECO \"C20\"]\n\n\
+                           1. e4 e5 2. Nf3 0-1\n";
+
+        let mut db = SqliteConnection::establish(":memory:").unwrap();
+        create_database(&mut db, "Test", "").unwrap();
+        import_games(
+            &mut db,
+            PGN.as_bytes(),
+            None,
+            ImportOptions::default(),
+            None,
+            &AtomicBool::new(false),
+            |_| {},
+        )
+        .unwrap();
+        let white_id: i32 = players::table
+            .filter(players::name.eq("A"))
+            .select(players::id)
+            .first(&mut db)
+            .unwrap();
+
+        diesel::update(games::table.filter(games::result.eq("0-1")))
+            .set(games::deleted.eq(true))
+            .execute(&mut db)
+            .unwrap();
+        update_info_counts(&mut db).unwrap();
+
+        assert_eq!(
+            get_info(&mut db, "GameCount").unwrap().as_deref(),
+            Some("1")
+        );
+        assert_eq!(
+            speed_distribution(&mut db).unwrap(),
+            vec![(Speed::Unknown, 1)]
+        );
+        let openings = player_openings(&mut db, white_id, PlayerColor::White).unwrap();
+        assert_eq!(openings.len(), 1);
+        assert_eq!(openings[0].games, 1);
+        assert_eq!(
+            openings[0].results,
+            Results {
+                won: 1,
+                lost: 0,
+                draw: 0
+            }
+        );
+    }
+
+    #[test]
+    fn query_rows_read_only() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE t (x INTEGER); INSERT INTO t VALUES (1);")
+            .unwrap();
+
+        let result = query_rows(&conn, "SELECT x FROM t; -- done\n").unwrap();
+        assert_eq!(result.rows, vec![serde_json::json!({ "x": 1 })]);
+        assert!(!result.truncated);
+
+        for sql in [
+            "DELETE FROM t",
+            "WITH y AS (SELECT 1) DELETE FROM t",
+            "SELECT x FROM t; DELETE FROM t",
+            "SELECT x FROM t; SELECT x FROM t",
+            "",
+        ] {
+            assert!(
+                matches!(query_rows(&conn, sql), Err(Error::InvalidQuery)),
+                "{sql}"
+            );
+        }
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM t", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn query_rows_limit() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        let sql = format!(
+            "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n LIMIT {})
+            SELECT x FROM n",
+            MAX_QUERY_ROWS + 1
+        );
+        let result = query_rows(&conn, &sql).unwrap();
+        assert_eq!(result.rows.len(), MAX_QUERY_ROWS);
+        assert!(result.truncated);
+    }
+
+    #[test]
+    fn read_games_illegal_moves() {
+        use std::io::Cursor;
+
+        const PGN: &str = "[White \"A\"]\n[Black \"B\"]\n\n1. e4 e5 2. Ke3 1-0\n\n\
+                           [FEN \"not a fen\"]\n\n1. e4 e5 2. Ke3 1-0\n";
+
+        let mut importer = Importer::new(None, ImportOptions::default());
+        assert_eq!(read_games(Cursor::new(PGN), &mut importer).count(), 0);
+        assert_eq!(importer.stats.illegal_move_games, 1);
+        assert_eq!(importer.stats.invalid_games, 1);
+    }
+
+    #[test]
+    fn read_games_latin1_names() {
+        use std::io::Cursor;
+
+        let pgn =
+            b"[White \"Andr\xe9\"]\n[Black \"M\xfcller\"]\n[Event \"Z\xfcrich\"]\n\n1. e4 e5 1-0\n";
+
+        let mut importer = Importer::new(None, ImportOptions::default());
+        let games: Vec<_> = read_games(Cursor::new(&pgn[..]), &mut importer)
+            .flatten()
+            .collect();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].white_name.as_deref(), Some("André"));
+        assert_eq!(games[0].black_name.as_deref(), Some("Müller"));
+        assert_eq!(games[0].event_name.as_deref(), Some("Zürich"));
+    }
+
+    #[test]
+    fn read_games_rating_diffs() {
+        use std::io::Cursor;
+
+        const PGN: &str = "[White \"A\"]\n[Black \"B\"]\n[WhiteRatingDiff \"+7\"]\n\
+                           [BlackRatingDiff \"-6\"]\n\n1. e4 e5 1-0\n";
+
+        let mut importer = Importer::new(None, ImportOptions::default());
+        let games: Vec<_> = read_games(Cursor::new(PGN), &mut importer)
+            .flatten()
+            .collect();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].white_rating_diff, Some(7));
+        assert_eq!(games[0].black_rating_diff, Some(-6));
+    }
+
+    #[test]
+    fn read_games_unfinished() {
+        use std::io::Cursor;
+
+        const PGN: &str = "[White \"A\"]\n[Black \"B\"]\n[Result \"*\"]\n\n1. e4 e5 *\n\n\
+                           [White \"C\"]\n[Black \"D\"]\n[Result \"1-0\"]\n\n1. d4 d5 1-0\n";
+
+        let mut importer = Importer::new(None, ImportOptions::default());
+        let games: Vec<_> = read_games(Cursor::new(PGN), &mut importer)
+            .flatten()
+            .collect();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].result, None);
+        assert_eq!(games[1].result.as_deref(), Some("1-0"));
+
+        let options = ImportOptions {
+            skip_unfinished: true,
+            ..Default::default()
+        };
+        let mut importer = Importer::new(None, options);
+        assert_eq!(read_games(Cursor::new(PGN), &mut importer).count(), 1);
+        assert_eq!(importer.stats.unfinished_games, 1);
+    }
+
+    #[test]
+    fn normalize_player_names() {
+        assert_eq!(normalize_name("Carlsen, Magnus"), "carlsen, magnus");
+        assert_eq!(normalize_name("Ivanchuk, Vassily"), "ivanchuk, vassily");
+        assert_eq!(normalize_name("Dvořák, Émile"), "dvorak, emile");
+    }
+
+    #[test]
+    fn read_games_variants() {
+        use std::io::Cursor;
+
+        const PGN: &str = concat!(
+            "[FEN \"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\"]\n",
+            "[Variant \"Chess960\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. O-O 1-0\n\n",
+            "[FEN \"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\"]\n\n",
+            "1. e4 e5 1-0\n",
+        );
+
+        let mut importer = Importer::new(None, ImportOptions::default());
+        let games: Vec<_> = read_games(Cursor::new(PGN), &mut importer)
+            .flatten()
+            .collect();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].variant.as_deref(), Some("Chess960"));
+        assert_eq!(
+            games[0].fen.as_deref(),
+            Some("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+        );
+        assert_eq!(games[0].moves.len(), 7);
+        assert_eq!(games[1].variant, None);
+        assert_eq!(games[1].fen, None);
+    }
+
+    #[test]
+    fn read_games_standard_fen() {
+        use std::io::Cursor;
+
+        const PGN: &str = concat!(
+            "[FEN \"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3 12 \"]\n\n",
+            "1. e4 e5 1-0\n\n",
+            "[FEN \"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Kkq - 0 1\"]\n\n",
+            "1. e4 e5 1-0\n",
+        );
+
+        let mut importer = Importer::new(None, ImportOptions::default());
+        let games: Vec<_> = read_games(Cursor::new(PGN), &mut importer)
+            .flatten()
+            .collect();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].fen, None);
+        assert_eq!(
+            games[1].fen.as_deref(),
+            Some("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Kkq - 0 1")
+        );
+    }
+
+    #[test]
+    fn puzzles() {
+        let fen = Some("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1");
+        assert!(is_puzzle(fen, None, 1));
+        assert!(is_puzzle(fen, Some("From Position"), 3));
+        assert!(!is_puzzle(fen, None, 80));
+        assert!(!is_puzzle(fen, Some("Chess960"), 1));
+        assert!(!is_puzzle(None, None, 1));
+    }
+
+    #[test]
+    fn read_ahead_chunks() {
+        use std::io::Cursor;
+
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+        let mut read = Vec::new();
+        ReadAhead::new(Box::new(Cursor::new(data.clone())), 7)
+            .read_to_end(&mut read)
+            .unwrap();
+        assert_eq!(read, data);
+    }
+
+    #[test]
+    fn read_games_truncated_gzip() {
+        use std::io::Cursor;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all("[White \"A\"]\n\n1. e4 e5 1-0\n".repeat(100).as_bytes())
+            .unwrap();
+        let mut compressed = encoder.finish().unwrap();
+        compressed.truncate(compressed.len() / 2);
+
+        let gzip = decompress(Cursor::new(compressed), Some("gz".as_ref())).unwrap();
+        let mut importer = Importer::new(None, ImportOptions::default());
+        let games = read_games(gzip, &mut importer).collect::<Result<Vec<_>, _>>();
+        assert!(games.is_err());
+    }
+
+    #[test]
+    fn needs_migration_missing_table() {
+        let mut db = SqliteConnection::establish(":memory:").unwrap();
+        assert!(!needs_migration(&mut db).unwrap());
+
+        db.batch_execute(CREATE_TABLES_SQL).unwrap();
+        assert!(!needs_migration(&mut db).unwrap());
+
+        db.batch_execute("DROP TABLE GameTags;").unwrap();
+        assert!(needs_migration(&mut db).unwrap());
+    }
+
+    #[test]
+    fn migrate_refuses_newer_version() {
+        assert!(is_newer_version("99.0.0"));
+        assert!(!is_newer_version(DATABASE_VERSION));
+        assert!(!is_newer_version("1.2.0"));
+        assert!(!is_newer_version("unknown"));
+
+        let mut db = SqliteConnection::establish(":memory:").unwrap();
+        db.batch_execute(CREATE_TABLES_SQL).unwrap();
+        set_info(&mut db, "Version", "99.0.0").unwrap();
+        assert!(matches!(migrate(&mut db), Err(Error::DatabaseTooNew)));
+        assert_eq!(get_version(&mut db).unwrap().as_deref(), Some("99.0.0"));
+    }
+
+    #[test]
+    fn backfill_puzzle_flags() {
+        const PGN: &str = "[White \"A\"]\n[Result \"1-0\"]\n[SetUp \"1\"]\n\
+                           [FEN \"6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1\"]\n\n1. Ra8# 1-0\n\n\
+                           [White \"B\"]\n[Result \"1-0\"]\n\n1. e4 e5 1-0\n";
+        let mut db = SqliteConnection::establish(":memory:").unwrap();
+        create_database(&mut db, "Test", "").unwrap();
+        import_games(
+            &mut db,
+            PGN.as_bytes(),
+            None,
+            ImportOptions::default(),
+            None,
+            &AtomicBool::new(false),
+            |_| {},
+        )
+        .unwrap();
+        db.batch_execute("UPDATE Games SET IsPuzzle = 0;").unwrap();
+        set_info(&mut db, PENDING_BACKFILLS, "IsPuzzle").unwrap();
+
+        let mut progress = Vec::new();
+        run_backfills(&mut db, |p| progress.push(p)).unwrap();
+        assert_eq!(progress, vec![100.0]);
+        assert!(pending_backfills(&mut db).unwrap().is_empty());
+        assert_eq!(
+            get_version(&mut db).unwrap().as_deref(),
+            Some(DATABASE_VERSION)
+        );
+
+        let puzzles: Vec<bool> = games::table
+            .order(games::id.asc())
+            .select(games::is_puzzle)
+            .load(&mut db)
+            .unwrap();
+        assert_eq!(puzzles, vec![true, false]);
+    }
+
+    #[test]
+    fn load_games_negative_limit() {
+        const PGN: &str = "[White \"A\"]\n[Result \"1-0\"]\n\n1. e4 e5 1-0\n\n\
+                           [White \"B\"]\n[Result \"0-1\"]\n\n1. d4 d5 0-1\n\n\
+                           [White \"C\"]\n[Result \"1-0\"]\n\n1. c4 c5 1-0\n";
+        let mut db = SqliteConnection::establish(":memory:").unwrap();
+        create_database(&mut db, "Test", "").unwrap();
+        import_games(
+            &mut db,
+            PGN.as_bytes(),
+            None,
+            ImportOptions::default(),
+            None,
+            &AtomicBool::new(false),
+            |_| {},
+        )
+        .unwrap();
+
+        let (games, has_more) = load_games(&mut db, &GameQuery::default(), -5, -1).unwrap();
+        assert_eq!(games.len(), 1);
+        assert!(has_more);
+
+        let query = GameQuery {
+            options: Some(QueryOptions {
+                page: Some(-1),
+                page_size: Some(-10),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let response = query_games(&mut db, &query).unwrap();
+        assert_eq!(response.data.len(), 1);
+        assert_eq!(response.count, Some(3));
+    }
+
+    #[test]
+    fn rename_players() {
+        const PGN: &str = "[White \"A\"]\n[Black \"B\"]\n[Result \"1-0\"]\n\n1. e4 e5 1-0\n";
+        let mut db = SqliteConnection::establish(":memory:").unwrap();
+        create_database(&mut db, "Test", "").unwrap();
+        import_games(
+            &mut db,
+            PGN.as_bytes(),
+            None,
+            ImportOptions::default(),
+            None,
+            &AtomicBool::new(false),
+            |_| {},
+        )
+        .unwrap();
+        let id: i32 = players::table
+            .filter(players::name.eq("A"))
+            .select(players::id)
+            .first(&mut db)
+            .unwrap();
+
+        assert!(matches!(
+            set_player_name(&mut db, id, " B "),
+            Err(Error::PlayerNameTaken)
+        ));
+        assert!(matches!(
+            set_player_name(&mut db, id, "  "),
+            Err(Error::EmptyPlayerName)
+        ));
+        assert!(matches!(
+            set_player_name(&mut db, 9999, "C"),
+            Err(Error::PlayerNotFound)
+        ));
+
+        set_player_name(&mut db, id, " C ").unwrap();
+        let name: Option<String> = players::table
+            .filter(players::id.eq(id))
+            .select(players::name)
+            .first(&mut db)
+            .unwrap();
+        assert_eq!(name.as_deref(), Some("C"));
+    }
+
+    #[test]
+    fn tag_with_other_filters() {
+        const PGN: &str = "[White \"A\"]\n[Result \"1-0\"]\n[ECO \"C20\"]\n\n1. e4 e5 1-0\n\n\
+                           [White \"B\"]\n[Result \"0-1\"]\n[ECO \"C20\"]\n\n1. e4 e5 0-1\n\n\
+                           [White \"C\"]\n[Result \"1-0\"]\n[ECO \"B20\"]\n\n1. e4 c5 1-0\n";
+        let mut db = SqliteConnection::establish(":memory:").unwrap();
+        create_database(&mut db, "Test", "").unwrap();
+        import_games(
+            &mut db,
+            PGN.as_bytes(),
+            None,
+            ImportOptions::default(),
+            None,
+            &AtomicBool::new(false),
+            |_| {},
+        )
+        .unwrap();
+        let ids: Vec<i32> = games::table
+            .order(games::id.asc())
+            .select(games::id)
+            .load(&mut db)
+            .unwrap();
+        diesel::insert_into(game_tags::table)
+            .values(vec![
+                (game_tags::game_id.eq(ids[0]), game_tags::tag.eq("review")),
+                (game_tags::game_id.eq(ids[1]), game_tags::tag.eq("review")),
+                (game_tags::game_id.eq(ids[2]), game_tags::tag.eq("study")),
+            ])
+            .execute(&mut db)
+            .unwrap();
+
+        let query = GameQuery {
+            tag: Some("review".to_string()),
+            outcome: Some("1-0".to_string()),
+            ..Default::default()
+        };
+        let response = query_games(&mut db, &query).unwrap();
+        assert_eq!(response.data.len(), 1);
+        assert_eq!(response.data[0].id, ids[0]);
+        assert_eq!(response.count, Some(1));
+        assert_eq!(count_matching_games(&mut db, &query).unwrap(), 1);
+
+        let query = GameQuery {
+            tag: Some("review".to_string()),
+            eco: Some("B2".to_string()),
+            ..Default::default()
+        };
+        assert!(query_games(&mut db, &query).unwrap().data.is_empty());
+        assert_eq!(count_matching_games(&mut db, &query).unwrap(), 0);
+    }
 }
@@ -16,12 +16,17 @@ pub struct Puzzle {
     pub nb_plays: i32,
 }
 
-#[derive(Default, Debug, Queryable, Serialize, Deserialize, Identifiable, Clone, Type)]
+#[derive(
+    Default, Debug, Queryable, QueryableByName, Serialize, Deserialize, Identifiable, Clone, Type,
+)]
 #[diesel(table_name = players)]
 pub struct Player {
     pub id: i32,
     pub name: Option<String>,
     pub elo: Option<i32>,
+    /// Lowercased name without diacritics, used for searching.
+    #[serde(skip)]
+    pub normalized_name: Option<String>,
 }
 
 #[derive(Insertable, Debug)]
@@ -29,6 +34,7 @@ pub struct Player {
 pub struct NewPlayer<'a> {
     pub name: &'a str,
     pub elo: Option<i32>,
+    pub normalized_name: Option<&'a str>,
 }
 
 struct White(pub Player);
@@ -58,6 +64,18 @@ pub struct Game {
     pub fen: Option<String>,
     pub moves: Vec<u8>,
     pub pawn_home: i32,
+    pub opening: Option<String>,
+    pub move_text: Option<String>,
+    pub variant: Option<String>,
+    pub deleted: bool,
+    pub annotator: Option<String>,
+    pub source: Option<String>,
+    pub final_position_hash: Option<i64>,
+    pub is_puzzle: bool,
+    pub white_rating_diff: Option<i32>,
+    pub black_rating_diff: Option<i32>,
+    pub final_material: Option<String>,
+    pub starred: bool,
 }
 
 #[derive(Insertable, Debug)]
@@ -81,6 +99,17 @@ pub struct NewGame<'a> {
     pub fen: Option<&'a str>,
     pub moves: &'a [u8],
     pub pawn_home: i32,
+    pub opening: Option<&'a str>,
+    pub move_text: Option<&'a str>,
+    pub variant: Option<&'a str>,
+    pub annotator: Option<&'a str>,
+    pub source: Option<&'a str>,
+    pub final_position_hash: Option<i64>,
+    pub is_puzzle: bool,
+    pub white_rating_diff: Option<i32>,
+    pub black_rating_diff: Option<i32>,
+    pub final_material: Option<&'a str>,
+    pub starred: bool,
 }
 
 #[derive(Default, Debug, Queryable, Serialize, Deserialize, Identifiable, Clone)]
@@ -130,11 +159,20 @@ pub struct NormalizedGame {
     pub black: String,
     pub black_id: i32,
     pub black_elo: Option<i32>,
-    pub result: Option<String>,
+    /// "*" for unfinished games.
+    pub result: String,
     pub time_control: Option<String>,
     pub eco: Option<String>,
+    pub opening: Option<String>,
     pub ply_count: Option<i32>,
     pub white_material: i32,
     pub black_material: i32,
     pub moves: String,
+    /// Full movetext, only stored for games with variations or annotations.
+    pub move_text: Option<String>,
+    pub variant: Option<String>,
+    pub annotator: Option<String>,
+    pub source: Option<String>,
+    #[serde(default)]
+    pub starred: bool,
 }
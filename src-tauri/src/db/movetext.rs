@@ -0,0 +1,164 @@
+use std::fmt::Write;
+
+use pgn_reader::{Nag, SanPlus};
+
+/// Rebuilds the PGN movetext of a game while it's being imported, so that the
+/// parts that don't fit in the encoded mainline, like variations, comments and
+/// NAGs, are kept.
+#[derive(Debug, Default)]
+pub struct MoveTextWriter {
+    text: String,
+    /// Ply of the next move in the mainline and in each open variation.
+    plies: Vec<u32>,
+    /// Whether the next move needs a move number even if it's Black's.
+    needs_number: bool,
+    /// Whether anything was written that the encoded mainline can't store.
+    has_extras: bool,
+}
+
+impl MoveTextWriter {
+    /// Starts the movetext of a new game, whose first move is played at `ply`.
+    /// Comments pushed since the last game finished are kept in front of it.
+    pub fn start(&mut self, ply: u32) {
+        self.plies = vec![ply];
+        self.needs_number = true;
+    }
+
+    /// Number of variations currently open.
+    pub fn depth(&self) -> usize {
+        self.plies.len().saturating_sub(1)
+    }
+
+    fn separate(&mut self) {
+        if !self.text.is_empty() && !self.text.ends_with('(') {
+            self.text.push(' ');
+        }
+    }
+
+    pub fn push_san(&mut self, san: &SanPlus) {
+        if self.plies.is_empty() {
+            return;
+        }
+        self.separate();
+
+        let ply = self.plies.last_mut().unwrap();
+        if *ply % 2 == 0 {
+            let _ = write!(self.text, "{}. ", *ply / 2 + 1);
+        } else if self.needs_number {
+            let _ = write!(self.text, "{}... ", *ply / 2 + 1);
+        }
+        let _ = write!(self.text, "{}", san);
+
+        *ply += 1;
+        self.needs_number = false;
+    }
+
+    pub fn push_nag(&mut self, nag: Nag) {
+        if self.plies.is_empty() {
+            return;
+        }
+        self.separate();
+        let _ = write!(self.text, "{}", nag);
+        self.has_extras = true;
+    }
+
+    pub fn push_comment(&mut self, comment: &str) {
+        let comment = comment.trim();
+        if comment.is_empty() {
+            return;
+        }
+        self.separate();
+        // A closing brace would end the comment early
+        let _ = write!(self.text, "{{{}}}", comment.replace('}', ")"));
+        self.needs_number = true;
+        self.has_extras = true;
+    }
+
+    /// Opens a variation, which replaces the last move played.
+    pub fn begin_variation(&mut self) {
+        let ply = self.plies.last().map_or(0, |ply| ply.saturating_sub(1));
+        self.plies.push(ply);
+        self.text.push_str(" (");
+        self.needs_number = true;
+        self.has_extras = true;
+    }
+
+    pub fn end_variation(&mut self) {
+        if self.plies.len() > 1 {
+            self.plies.pop();
+            self.text.push(')');
+            self.needs_number = true;
+        }
+    }
+
+    /// Returns the movetext of the game, or `None` if it has nothing the
+    /// encoded mainline doesn't already store.
+    pub fn finish(&mut self) -> Option<String> {
+        self.plies.clear();
+        let text = std::mem::take(&mut self.text);
+        std::mem::take(&mut self.has_extras).then_some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn san(s: &str) -> SanPlus {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn variations() {
+        let mut writer = MoveTextWriter::default();
+        writer.start(0);
+        writer.push_san(&san("e4"));
+        writer.begin_variation();
+        writer.push_san(&san("d4"));
+        writer.push_san(&san("d5"));
+        writer.end_variation();
+        writer.push_san(&san("e5"));
+        writer.push_san(&san("Nf3"));
+        assert_eq!(
+            writer.finish(),
+            Some("1. e4 (1. d4 d5) 1... e5 2. Nf3".to_string())
+        );
+    }
+
+    #[test]
+    fn annotations() {
+        let mut writer = MoveTextWriter::default();
+        writer.start(0);
+        writer.push_comment("Game notes");
+        writer.push_san(&san("e4"));
+        writer.push_nag(Nag(1));
+        writer.push_comment("Best by test");
+        writer.push_san(&san("e5"));
+        assert_eq!(
+            writer.finish(),
+            Some("{Game notes} 1. e4 $1 {Best by test} 1... e5".to_string())
+        );
+    }
+
+    #[test]
+    fn leading_comment() {
+        let mut writer = MoveTextWriter::default();
+        writer.push_comment("Before the game");
+        writer.start(0);
+        writer.push_san(&san("e4"));
+        assert_eq!(writer.finish(), Some("{Before the game} 1. e4".to_string()));
+
+        writer.start(0);
+        writer.push_san(&san("d4"));
+        assert_eq!(writer.finish(), None);
+    }
+
+    #[test]
+    fn no_variations() {
+        let mut writer = MoveTextWriter::default();
+        writer.start(0);
+        writer.push_san(&san("e4"));
+        writer.push_san(&san("e5"));
+        assert_eq!(writer.finish(), None);
+    }
+}
@@ -1,5 +1,15 @@
 use crate::db::models::{Event, Game, NewEvent, NewGame, NewPlayer, NewSite, Player, Site};
 use diesel::prelude::*;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Lowercases a player name and strips its diacritics, so "Émile" and "emile"
+/// compare equal.
+pub fn normalize_name(name: &str) -> String {
+    name.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
 
 /// Creates a new player in the database, and returns the player's ID.
 /// If the player already exists, returns the ID of the existing player.
@@ -9,7 +19,12 @@ pub fn create_player(
 ) -> Result<Player, diesel::result::Error> {
     use crate::db::schema::players;
 
-    let new_player = NewPlayer { name, elo: None };
+    let normalized_name = normalize_name(name);
+    let new_player = NewPlayer {
+        name,
+        elo: None,
+        normalized_name: Some(&normalized_name),
+    };
 
     let player = diesel::insert_or_ignore_into(players::table)
         .values(&new_player)
@@ -71,3 +86,44 @@ pub fn create_game(
         .values(&game)
         .get_result(conn)
 }
+
+/// Deletes a player if no game references it anymore, and returns whether it
+/// was deleted. The "Unknown" placeholder player is always kept.
+pub fn delete_player_if_orphaned(
+    conn: &mut SqliteConnection,
+    id: i32,
+) -> Result<bool, diesel::result::Error> {
+    use crate::db::schema::{games, players};
+
+    if id == 0 {
+        return Ok(false);
+    }
+
+    let game_count: i64 = games::table
+        .filter(games::white_id.eq(id).or(games::black_id.eq(id)))
+        .count()
+        .get_result(conn)?;
+    if game_count > 0 {
+        return Ok(false);
+    }
+
+    let deleted = diesel::delete(players::table.filter(players::id.eq(id))).execute(conn)?;
+    Ok(deleted > 0)
+}
+
+/// Sets a value in the Info table, replacing the previous one if it exists.
+pub fn set_info(
+    conn: &mut SqliteConnection,
+    name: &str,
+    value: &str,
+) -> Result<(), diesel::result::Error> {
+    use crate::db::schema::info;
+
+    diesel::insert_into(info::table)
+        .values((info::name.eq(name), info::value.eq(value)))
+        .on_conflict(info::name)
+        .do_update()
+        .set(info::value.eq(value))
+        .execute(conn)?;
+    Ok(())
+}
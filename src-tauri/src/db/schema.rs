@@ -19,6 +19,8 @@ diesel::table! {
         name -> Nullable<Text>,
         #[sql_name = "Elo"]
         elo -> Nullable<Integer>,
+        #[sql_name = "NormalizedName"]
+        normalized_name -> Nullable<Text>,
     }
 }
 
@@ -63,6 +65,30 @@ diesel::table! {
         moves -> Binary,
         #[sql_name = "PawnHome"]
         pawn_home -> Integer,
+        #[sql_name = "Opening"]
+        opening -> Nullable<Text>,
+        #[sql_name = "MoveText"]
+        move_text -> Nullable<Text>,
+        #[sql_name = "Variant"]
+        variant -> Nullable<Text>,
+        #[sql_name = "Deleted"]
+        deleted -> Bool,
+        #[sql_name = "Annotator"]
+        annotator -> Nullable<Text>,
+        #[sql_name = "Source"]
+        source -> Nullable<Text>,
+        #[sql_name = "FinalPositionHash"]
+        final_position_hash -> Nullable<BigInt>,
+        #[sql_name = "IsPuzzle"]
+        is_puzzle -> Bool,
+        #[sql_name = "WhiteRatingDiff"]
+        white_rating_diff -> Nullable<Integer>,
+        #[sql_name = "BlackRatingDiff"]
+        black_rating_diff -> Nullable<Integer>,
+        #[sql_name = "FinalMaterial"]
+        final_material -> Nullable<Text>,
+        #[sql_name = "Starred"]
+        starred -> Bool,
     }
 }
 
@@ -110,7 +136,19 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    #[sql_name = "GameTags"]
+    game_tags (game_id, tag) {
+        #[sql_name = "GameID"]
+        game_id -> Integer,
+        #[sql_name = "Tag"]
+        tag -> Text,
+    }
+}
+
 diesel::joinable!(games -> events (event_id));
 diesel::joinable!(games -> sites (site_id));
 
-diesel::allow_tables_to_appear_in_same_query!(comments, events, games, info, players, sites,);
+diesel::allow_tables_to_appear_in_same_query!(
+    comments, events, game_tags, games, info, players, sites,
+);
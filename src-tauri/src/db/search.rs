@@ -167,10 +167,13 @@ pub struct PositionStats {
     pub black: i32,
 }
 
+/// Returns the move played after `query` is matched in the game, or "*" if the
+/// game ended there. Only the first `max_ply` plies are searched, if given.
 fn get_move_after_match(
     move_blob: &Vec<u8>,
     fen: &Option<String>,
     query: &PositionQuery,
+    max_ply: Option<usize>,
 ) -> Result<Option<String>, Error> {
     let mut chess = if let Some(fen) = fen {
         let fen = Fen::from_ascii(fen.as_bytes())?;
@@ -189,6 +192,9 @@ fn get_move_after_match(
     }
 
     for (i, byte) in move_blob.iter().enumerate() {
+        if max_ply.is_some_and(|max_ply| i >= max_ply) {
+            return Ok(None);
+        }
         let m = decode_move(*byte, &chess).unwrap();
         chess.play_unchecked(&m);
         let board = chess.board();
@@ -249,6 +255,7 @@ pub async fn search_position(
                 games::white_material,
                 games::black_material,
             ))
+            .filter(games::deleted.eq(false))
             .load(db)?;
 
         info!("got {} games: {:?}", games.len(), start.elapsed());
@@ -326,7 +333,9 @@ pub async fn search_position(
 
             if let Some(position_query) = &query.position {
                 if position_query.can_reach(&end_material, *end_pawn_home as u16) {
-                    if let Ok(Some(m)) = get_move_after_match(game, fen, position_query) {
+                    if let Ok(Some(m)) =
+                        get_move_after_match(game, fen, position_query, query.max_ply)
+                    {
                         if sample_games.lock().unwrap().len() < 10 {
                             sample_games.lock().unwrap().push(*id);
                         }
@@ -422,6 +431,7 @@ pub async fn is_position_in_db(
                 games::white_material,
                 games::black_material,
             ))
+            .filter(games::deleted.eq(false))
             .load(db)?;
 
         info!("got {} games: {:?}", games.len(), start.elapsed());
@@ -449,7 +459,7 @@ pub async fn is_position_in_db(
             };
             if let Some(position_query) = &query.position {
                 position_query.can_reach(&end_material, *end_pawn_home as u16)
-                    && get_move_after_match(game, fen, position_query)
+                    && get_move_after_match(game, fen, position_query, query.max_ply)
                         .unwrap_or(None)
                         .is_some()
             } else {
@@ -559,18 +569,18 @@ mod tests {
 
         let query =
             PositionQuery::exact_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR").unwrap();
-        let result = get_move_after_match(&game, &None, &query).unwrap();
+        let result = get_move_after_match(&game, &None, &query, None).unwrap();
         assert_eq!(result, Some("e4".to_string()));
 
         let query =
             PositionQuery::exact_from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR").unwrap();
-        let result = get_move_after_match(&game, &None, &query).unwrap();
+        let result = get_move_after_match(&game, &None, &query, None).unwrap();
         assert_eq!(result, Some("e5".to_string()));
 
         let query =
             PositionQuery::exact_from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR")
                 .unwrap();
-        let result = get_move_after_match(&game, &None, &query).unwrap();
+        let result = get_move_after_match(&game, &None, &query, None).unwrap();
         assert_eq!(result, Some("*".to_string()));
     }
 
@@ -579,7 +589,19 @@ mod tests {
         let game = vec![12, 12]; // 1. e4 e5
 
         let query = PositionQuery::partial_from_fen("8/pppppppp/8/8/8/8/PPPPPPPP/8").unwrap();
-        let result = get_move_after_match(&game, &None, &query).unwrap();
+        let result = get_move_after_match(&game, &None, &query, None).unwrap();
         assert_eq!(result, Some("e4".to_string()));
     }
+
+    #[test]
+    fn get_move_after_match_max_ply_test() {
+        let game = vec![12, 12]; // 1. e4 e5
+
+        let query =
+            PositionQuery::exact_from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR").unwrap();
+        let result = get_move_after_match(&game, &None, &query, Some(0)).unwrap();
+        assert_eq!(result, None);
+        let result = get_move_after_match(&game, &None, &query, Some(1)).unwrap();
+        assert_eq!(result, Some("e5".to_string()));
+    }
 }
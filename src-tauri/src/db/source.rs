@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+/// Where a game was played, derived from its Site header.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Source {
+    Lichess,
+    ChessCom,
+    #[serde(rename = "OTB")]
+    Otb,
+    Other,
+}
+
+/// Hints that a Site header names an online server rather than a place.
+const ONLINE_HINTS: &[&str] = &[
+    "http",
+    "www.",
+    ".com",
+    ".org",
+    ".net",
+    "chessclub",
+    "playchess",
+    "online",
+];
+
+impl Source {
+    /// Classifies the value of a PGN Site header. Lichess and Chess.com are
+    /// recognized by name, other online servers end up as `Other`, and
+    /// anything else is taken to be the place of an over-the-board game.
+    pub fn from_site(site: &str) -> Source {
+        let site = site.trim().to_lowercase();
+        if site.contains("lichess") {
+            Source::Lichess
+        } else if site.contains("chess.com") {
+            Source::ChessCom
+        } else if site.is_empty()
+            || site == "?"
+            || site == "unknown"
+            || ONLINE_HINTS.iter().any(|hint| site.contains(hint))
+        {
+            Source::Other
+        } else {
+            Source::Otb
+        }
+    }
+
+    /// Name stored in the Source column, the same as its serialized form.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Source::Lichess => "Lichess",
+            Source::ChessCom => "ChessCom",
+            Source::Otb => "OTB",
+            Source::Other => "Other",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn site_source() {
+        assert_eq!(
+            Source::from_site("https://lichess.org/abcdefgh"),
+            Source::Lichess
+        );
+        assert_eq!(Source::from_site("Chess.com"), Source::ChessCom);
+        assert_eq!(
+            Source::from_site("https://www.chess.com/game/live/1"),
+            Source::ChessCom
+        );
+        assert_eq!(Source::from_site("Wijk aan Zee NED"), Source::Otb);
+        assert_eq!(Source::from_site("freechess.org"), Source::Other);
+        assert_eq!(Source::from_site("?"), Source::Other);
+    }
+}
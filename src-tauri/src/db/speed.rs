@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// Time per move from which a game is played by correspondence.
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Time control category of a game, following the Lichess definitions.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Speed {
+    UltraBullet,
+    Bullet,
+    Blitz,
+    Rapid,
+    Classical,
+    Correspondence,
+    Unknown,
+}
+
+/// Upper bounds of the estimated duration of each speed, in seconds, where the
+/// estimated duration is the initial time plus 40 times the increment. Longer
+/// games are classical.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SpeedThresholds {
+    pub ultra_bullet: u64,
+    pub bullet: u64,
+    pub blitz: u64,
+    pub rapid: u64,
+}
+
+impl Default for SpeedThresholds {
+    fn default() -> Self {
+        Self {
+            ultra_bullet: 30,
+            bullet: 180,
+            blitz: 480,
+            rapid: 1500,
+        }
+    }
+}
+
+impl SpeedThresholds {
+    /// Whether each bound is above the one of the faster speed, without which
+    /// some speeds could never be reached.
+    pub fn is_increasing(&self) -> bool {
+        self.ultra_bullet < self.bullet && self.bullet < self.blitz && self.blitz < self.rapid
+    }
+
+    /// Classifies a game by its initial time and increment, in seconds.
+    pub fn classify(&self, seconds: u64, increment: u64) -> Speed {
+        let total = seconds.saturating_add(increment.saturating_mul(40));
+
+        if total < self.ultra_bullet {
+            Speed::UltraBullet
+        } else if total < self.bullet {
+            Speed::Bullet
+        } else if total < self.blitz {
+            Speed::Blitz
+        } else if total < self.rapid {
+            Speed::Rapid
+        } else {
+            Speed::Classical
+        }
+    }
+}
+
+impl Speed {
+    /// Classifies the value of a PGN TimeControl tag. Besides "180+2" and
+    /// "1800", this accepts multi-stage controls like "40/7200:1800+30", which
+    /// are classified by their first stage, sandclock controls like "*180" and
+    /// per-move controls like "1/86400", which are correspondence when there
+    /// is at least a day per move. "-" means the game had no clock, as in
+    /// correspondence games, and "?" that the time control is unknown.
+    pub fn from_time_control(
+        time_control: &str,
+        thresholds: &SpeedThresholds,
+    ) -> Result<Speed, Error> {
+        let time_control = time_control.trim();
+        let invalid = || Error::InvalidTimeControl(time_control.to_string());
+        match time_control {
+            "-" => return Ok(Speed::Correspondence),
+            "?" => return Ok(Speed::Unknown),
+            _ => {}
+        }
+
+        let stage = time_control.split(':').next().unwrap_or_default();
+        if let Some(seconds) = stage.strip_prefix('*') {
+            let seconds = seconds.parse().map_err(|_| invalid())?;
+            return Ok(thresholds.classify(seconds, 0));
+        }
+
+        let (stage, increment) = match stage.split_once('+') {
+            Some((stage, increment)) => (stage, increment.parse().map_err(|_| invalid())?),
+            None => (stage, 0),
+        };
+        let seconds = match stage.split_once('/') {
+            Some((moves, seconds)) => {
+                let moves: u64 = moves.parse().map_err(|_| invalid())?;
+                let seconds: u64 = seconds.parse().map_err(|_| invalid())?;
+                if moves == 0 {
+                    return Err(invalid());
+                }
+                if seconds / moves >= SECONDS_PER_DAY {
+                    return Ok(Speed::Correspondence);
+                }
+                seconds
+            }
+            None => stage.parse().map_err(|_| invalid())?,
+        };
+        Ok(thresholds.classify(seconds, increment))
+    }
+
+    /// Like [`Speed::from_time_control`], but games without a TimeControl tag
+    /// or with one that can't be parsed are `Unknown`.
+    pub fn classify(time_control: Option<&str>, thresholds: &SpeedThresholds) -> Speed {
+        time_control
+            .and_then(|time_control| Speed::from_time_control(time_control, thresholds).ok())
+            .unwrap_or(Speed::Unknown)
+    }
+
+    /// A common time control of this speed, as the initial time and the
+    /// increment in seconds. Correspondence and unknown games have none.
+    pub fn typical_time_control(self) -> Option<(u64, u64)> {
+        match self {
+            Speed::UltraBullet => Some((15, 0)),
+            Speed::Bullet => Some((60, 0)),
+            Speed::Blitz => Some((180, 2)),
+            Speed::Rapid => Some((600, 5)),
+            Speed::Classical => Some((1800, 20)),
+            Speed::Correspondence | Speed::Unknown => None,
+        }
+    }
+
+    /// Rough estimate of how long a game of this speed with `plies` plies
+    /// lasted, in seconds. Both players are assumed to spread their initial
+    /// time over 40 moves and to use every increment.
+    pub fn estimated_duration(self, plies: u64) -> Option<u64> {
+        let (initial, increment) = self.typical_time_control()?;
+        let moves = plies.div_ceil(2);
+        Some(2 * initial * moves.min(40) / 40 + plies * increment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_control_speed() {
+        let thresholds = SpeedThresholds::default();
+        let speed = |time_control| Speed::from_time_control(time_control, &thresholds).ok();
+        assert_eq!(speed("15+0"), Some(Speed::UltraBullet));
+        assert_eq!(speed("60+0"), Some(Speed::Bullet));
+        assert_eq!(speed("180+2"), Some(Speed::Blitz));
+        assert_eq!(speed("600+5"), Some(Speed::Rapid));
+        assert_eq!(speed("1800"), Some(Speed::Classical));
+        assert_eq!(speed("-"), Some(Speed::Correspondence));
+        assert_eq!(speed("?"), Some(Speed::Unknown));
+    }
+
+    #[test]
+    fn complex_time_controls() {
+        let thresholds = SpeedThresholds::default();
+        let speed = |time_control| Speed::from_time_control(time_control, &thresholds).ok();
+        assert_eq!(speed("40/7200:1800+30"), Some(Speed::Classical));
+        assert_eq!(speed("40/300:60"), Some(Speed::Blitz));
+        assert_eq!(speed("*180"), Some(Speed::Blitz));
+        assert_eq!(speed("*20"), Some(Speed::UltraBullet));
+        assert_eq!(speed("1/86400"), Some(Speed::Correspondence));
+        assert_eq!(speed("1/259200"), Some(Speed::Correspondence));
+        assert_eq!(speed(" 300+3 "), Some(Speed::Blitz));
+
+        let huge_increment = format!("60+{}", u64::MAX);
+        assert_eq!(
+            Speed::from_time_control(&huge_increment, &thresholds).ok(),
+            Some(Speed::Classical)
+        );
+    }
+
+    #[test]
+    fn invalid_time_controls() {
+        let thresholds = SpeedThresholds::default();
+        for time_control in ["", "abc", "180+", "+2", "0/600", "*", "180+2+1"] {
+            assert!(Speed::from_time_control(time_control, &thresholds).is_err());
+        }
+        assert_eq!(Speed::classify(Some("abc"), &thresholds), Speed::Unknown);
+        assert_eq!(Speed::classify(None, &thresholds), Speed::Unknown);
+    }
+
+    #[test]
+    fn custom_thresholds() {
+        let thresholds = SpeedThresholds {
+            blitz: 600,
+            ..SpeedThresholds::default()
+        };
+        assert_eq!(Speed::classify(Some("300+5"), &thresholds), Speed::Blitz);
+        assert_eq!(
+            Speed::classify(Some("300+5"), &SpeedThresholds::default()),
+            Speed::Rapid
+        );
+
+        assert!(SpeedThresholds::default().is_increasing());
+        let overlapping = SpeedThresholds {
+            blitz: 1500,
+            ..SpeedThresholds::default()
+        };
+        assert!(!overlapping.is_increasing());
+    }
+
+    #[test]
+    fn estimated_duration() {
+        assert_eq!(Speed::Blitz.estimated_duration(80), Some(520));
+        assert_eq!(Speed::Bullet.estimated_duration(40), Some(60));
+        assert_eq!(Speed::Bullet.estimated_duration(200), Some(120));
+        assert_eq!(Speed::Correspondence.estimated_duration(80), None);
+    }
+}
@@ -18,6 +18,9 @@ pub enum Error {
     #[error(transparent)]
     XmlDeserialize(#[from] quick_xml::de::DeError),
 
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+
     #[error(transparent)]
     ParseInt(#[from] std::num::ParseIntError),
 
@@ -54,9 +57,18 @@ pub enum Error {
     #[error(transparent)]
     R2d2(#[from] diesel::r2d2::PoolError),
 
+    #[error(transparent)]
+    DieselConnection(#[from] diesel::ConnectionError),
+
+    #[error(transparent)]
+    Rusqlite(#[from] rusqlite::Error),
+
     #[error(transparent)]
     SystemTime(#[from] std::time::SystemTimeError),
 
+    #[error(transparent)]
+    Join(#[from] tokio::task::JoinError),
+
     #[error("No stdin")]
     NoStdin,
 
@@ -72,6 +84,36 @@ pub enum Error {
     #[error("Search stopped")]
     SearchStopped,
 
+    #[error("Only a single SELECT or PRAGMA statement can be run")]
+    InvalidQuery,
+
+    #[error("Invalid time control: {0}")]
+    InvalidTimeControl(String),
+
+    #[error("Speed thresholds must be increasing from ultrabullet to rapid")]
+    InvalidSpeedThresholds,
+
+    #[error("Lines can be at most {0} plies long")]
+    LineTooLong(usize),
+
+    #[error("Games can't be deleted by position")]
+    PositionFilterUnsupported,
+
+    #[error("Invalid path")]
+    InvalidPath,
+
+    #[error("Database not found")]
+    DatabaseNotFound,
+
+    #[error("A database with this name already exists")]
+    DatabaseExists,
+
+    #[error("The database needs to be upgraded, but it can't be written to")]
+    DatabaseNeedsMigration,
+
+    #[error("The database was created by a newer version of the app")]
+    DatabaseTooNew,
+
     #[error("Missing reference database")]
     MissingReferenceDatabase,
 
@@ -86,6 +128,33 @@ pub enum Error {
 
     #[error("Players aren't the same. They have played against each other")]
     NotDistinctPlayers,
+
+    #[error("A player with this name already exists")]
+    PlayerNameTaken,
+
+    #[error("Player names can't be empty")]
+    EmptyPlayerName,
+
+    #[error("Player not found")]
+    PlayerNotFound,
+
+    #[error("Tags can't be empty")]
+    EmptyTag,
+
+    #[error("Invalid move encoding")]
+    InvalidMoveEncoding,
+
+    #[error("Import cancelled")]
+    ImportCancelled,
+
+    #[error("An import into this database is in progress")]
+    ImportInProgress,
+
+    #[error("Invalid username")]
+    InvalidUsername,
+
+    #[error("Too many requests, try again in a minute")]
+    RateLimited,
 }
 
 impl serde::Serialize for Error {
@@ -15,7 +15,7 @@ mod pgn;
 mod puzzle;
 
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::{atomic::AtomicBool, Arc, Mutex};
 use std::{fs::create_dir_all, path::Path};
 
 use chess::{BestMovesPayload, EngineProcess, ReportProgress};
@@ -38,9 +38,18 @@ use crate::chess::{
     analyze_game, get_engine_config, get_engine_logs, kill_engine, kill_engines, stop_engine,
 };
 use crate::db::{
-    clear_games, convert_pgn, create_indexes, delete_database, delete_db_game, delete_empty_games,
-    delete_indexes, export_to_pgn, get_player, get_players_game_info, get_tournaments,
-    search_position,
+    add_tag, append_pgn, cancel_import, classify_openings, clear_database, clear_games,
+    convert_pgn, count_games, create_indexes, delete_database, delete_db_game, delete_empty_games,
+    delete_games, delete_indexes, estimate_import, export_games, export_to_pgn,
+    find_duplicate_games, find_games_by_final_position, get_activity_by_year, get_game_moves,
+    get_head_to_head, get_move_tree, get_opponent_count, get_performance_rating, get_player,
+    get_player_games, get_player_openings, get_player_time_stats, get_players_game_info,
+    get_players_game_info_bulk, get_rating_change_history, get_rating_history, get_recent_games,
+    get_speed_distribution, get_tags, get_top_players, get_tournaments, get_weakest_openings,
+    get_winrate_by_opponent_rating, import_from_chesscom, import_from_lichess, list_databases,
+    list_events, merge_databases, migrate_database, purge_deleted, reclassify_speeds,
+    recompute_ply_counts, remove_tag, rename_player, restore_game, run_query, search_position,
+    split_database, toggle_star, vacuum_database,
 };
 use crate::fide::{download_fide_db, find_fide_player};
 use crate::fs::{append_to_file, set_file_as_executable, DownloadProgress};
@@ -51,7 +60,8 @@ use crate::puzzle::{get_puzzle, get_puzzle_db_info};
 use crate::{
     chess::get_best_moves,
     db::{
-        delete_duplicated_games, edit_db_info, get_db_info, get_games, get_players, merge_players,
+        delete_duplicated_games, edit_db_info, get_db_info, get_game, get_games, get_players,
+        merge_players,
     },
     fs::{download_file, file_exists, get_file_metadata},
     opening::{get_opening_from_fen, get_opening_from_name, search_opening_name},
@@ -86,6 +96,7 @@ pub struct AppState {
     #[derivative(Default(value = "Arc::new(Semaphore::new(2))"))]
     new_request: Arc<Semaphore>,
     pgn_offsets: DashMap<String, Vec<u64>>,
+    import_cancellations: DashMap<String, Arc<AtomicBool>>,
     fide_players: RwLock<Vec<FidePlayer>>,
     engine_processes: DashMap<(String, String), Arc<tokio::sync::Mutex<EngineProcess>>>,
     auth: AuthState,
@@ -263,6 +274,7 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             download_file,
             get_games,
+            get_game,
             get_players,
             get_tournaments,
             get_db_info,
@@ -287,7 +299,52 @@ fn main() {
             search_opening_name,
             delete_db_game,
             delete_empty_games,
-            export_to_pgn
+            export_to_pgn,
+            export_games,
+            get_head_to_head,
+            get_performance_rating,
+            get_rating_history,
+            get_player_openings,
+            import_from_lichess,
+            merge_databases,
+            recompute_ply_counts,
+            cancel_import,
+            get_speed_distribution,
+            get_top_players,
+            vacuum_database,
+            clear_database,
+            classify_openings,
+            migrate_database,
+            delete_games,
+            restore_game,
+            purge_deleted,
+            append_pgn,
+            find_duplicate_games,
+            get_player_games,
+            get_opponent_count,
+            get_game_moves,
+            get_player_time_stats,
+            get_recent_games,
+            run_query,
+            reclassify_speeds,
+            get_activity_by_year,
+            import_from_chesscom,
+            find_games_by_final_position,
+            list_databases,
+            get_winrate_by_opponent_rating,
+            rename_player,
+            add_tag,
+            remove_tag,
+            get_tags,
+            list_events,
+            estimate_import,
+            count_games,
+            get_players_game_info_bulk,
+            get_rating_change_history,
+            get_weakest_openings,
+            split_database,
+            get_move_tree,
+            toggle_star
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,4 +1,6 @@
 use log::info;
+use std::collections::HashMap;
+
 use serde::{ser::SerializeStruct, Deserialize, Serialize};
 use shakmaty::{fen::Fen, san::San, Chess, EnPassantMode, Position, Setup};
 
@@ -74,6 +76,14 @@ pub fn get_opening_from_setup(setup: Setup) -> Result<String, Error> {
         .ok_or_else(|| Error::NoOpeningFound)
 }
 
+/// Returns the ECO code and name of the standard opening reaching `setup`.
+pub fn get_eco_from_setup(setup: &Setup) -> Option<(&'static str, &'static str)> {
+    let fen = Fen::from_setup(setup.clone()).to_string();
+    OPENINGS_BY_FEN
+        .get(&fen)
+        .map(|&i| (OPENINGS[i].eco.as_str(), OPENINGS[i].name.as_str()))
+}
+
 #[tauri::command]
 pub async fn search_opening_name(query: String) -> Result<Vec<Opening>, Error> {
     let lower_query = query.to_lowercase();
@@ -154,6 +164,16 @@ lazy_static! {
         }
         positions
     };
+    static ref OPENINGS_BY_FEN: HashMap<String, usize> = {
+        let mut openings = HashMap::new();
+        for (i, opening) in OPENINGS.iter().enumerate() {
+            if opening.pgn.is_some() {
+                let fen = Fen::from_setup(opening.setup.clone()).to_string();
+                openings.entry(fen).or_insert(i);
+            }
+        }
+        openings
+    };
 }
 
 #[cfg(test)]
@@ -167,4 +187,16 @@ mod tests {
                 .unwrap();
         assert_eq!(opening, "Bongcloud Attack");
     }
+
+    #[test]
+    fn test_get_eco() {
+        let fen: Fen = "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            get_eco_from_setup(&fen.into_setup()),
+            Some(("B20", "Sicilian Defense"))
+        );
+        assert_eq!(get_eco_from_setup(&Setup::default()), None);
+    }
 }
@@ -98,7 +98,7 @@ try {
     else return { status: "error", error: e  as any };
 }
 },
-async getPlayersGameInfo(file: string, id: number) : Promise<__Result__<{ won: number; lost: number; draw: number; data_per_month: ([string, MonthData])[]; white_openings: ([string, Results])[]; black_openings: ([string, Results])[] }, string>> {
+async getPlayersGameInfo(file: string, id: number) : Promise<__Result__<{ won: number; lost: number; draw: number; white_results: Results; black_results: Results; data_per_month: ([string, MonthData])[]; white_openings: ([string, Results])[]; black_openings: ([string, Results])[] }, string>> {
 try {
     return { status: "ok", data: await TAURI_INVOKE("plugin:tauri-specta|get_players_game_info", { file, id }) };
 } catch (e) {
@@ -138,9 +138,9 @@ try {
     else return { status: "error", error: e  as any };
 }
 },
-async convertPgn(file: string, dbPath: string, timestamp: number | null, title: string, description: string | null) : Promise<__Result__<null, string>> {
+async convertPgn(file: string, dbPath: string, timestamp: number | null, title: string, description: string | null, options: ImportOptions | null) : Promise<__Result__<ImportStats, string>> {
 try {
-    return { status: "ok", data: await TAURI_INVOKE("plugin:tauri-specta|convert_pgn", { file, dbPath, timestamp, title, description }) };
+    return { status: "ok", data: await TAURI_INVOKE("plugin:tauri-specta|convert_pgn", { file, dbPath, timestamp, title, description, options }) };
 } catch (e) {
     if(e instanceof Error) throw e;
     else return { status: "error", error: e  as any };
@@ -178,6 +178,8 @@ export type DownloadProgress = { progress: number; id: string; finished: boolean
 export type EngineOption = { name: string; value: string }
 export type EngineOptions = { fen: string; moves: string[]; extraOptions: EngineOption[] }
 export type GoMode = { t: "PlayersTime"; c: PlayersTime } | { t: "Depth"; c: number } | { t: "Time"; c: number } | { t: "Nodes"; c: number } | { t: "Infinite" }
+export type ImportOptions = { keep_variations: boolean; keep_annotations: boolean; require_ratings: boolean; skip_unfinished: boolean; skip_bots: boolean; skip_duplicates: boolean; batch_size: number | null; read_chunk_size: number | null; safe_import: boolean }
+export type ImportStats = { total_games: number; imported_games: number; invalid_games: number; illegal_move_games: number; duplicate_games: number; bot_games: number; unrated_games: number; older_games: number; unfinished_games: number; resumed_games: number; failed_archives: number }
 export type MonthData = { count: number; avg_elo: number }
 export type PlayersTime = { white: number; black: number; winc: number; binc: number }
 export type ReportProgress = { progress: number; id: string; finished: boolean }
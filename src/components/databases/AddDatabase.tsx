@@ -52,7 +52,14 @@ function AddDatabase({
     setLoading(true);
     const dbPath = await resolve(await appDataDir(), "db", `${title}.db3`);
     unwrap(
-      await commands.convertPgn(path, dbPath, null, title, description ?? null),
+      await commands.convertPgn(
+        path,
+        dbPath,
+        null,
+        title,
+        description ?? null,
+        null,
+      ),
     );
     setDatabases(await getDatabases());
     setLoading(false);
@@ -146,7 +153,13 @@ function AddDatabase({
                   filters: [
                     {
                       name: "PGN file",
-                      extensions: ["pgn", "pgn.zst"],
+                      extensions: [
+                        "pgn",
+                        "pgn.zst",
+                        "pgn.bz2",
+                        "pgn.gz",
+                        "pgn.xz",
+                      ],
                     },
                   ],
                 });
@@ -8,6 +8,8 @@ import { useTranslation } from "react-i18next";
 type Progress = {
   total: number;
   elapsed: number;
+  bytesRead: number;
+  totalBytes: number;
 };
 
 function ConvertButton({
@@ -24,7 +26,12 @@ function ConvertButton({
     async function getProgress() {
       await listen<number[]>("convert_progress", (event) => {
         const progress = event.payload;
-        setProgress({ total: progress[0], elapsed: progress[1] / 1000 });
+        setProgress({
+          total: progress[0],
+          elapsed: progress[1] / 1000,
+          bytesRead: progress[2],
+          totalBytes: progress[3],
+        });
       });
     }
     getProgress();
@@ -49,6 +56,11 @@ function ConvertButton({
         {progress && loading && (
           <Box style={{ display: "flex", justifyContent: "space-around" }}>
             <Text fz="xs">{progress.total} games</Text>
+            {progress.totalBytes > 0 && (
+              <Text fz="xs">
+                {((progress.bytesRead / progress.totalBytes) * 100).toFixed(0)}%
+              </Text>
+            )}
             <Text fz="xs" mb={10}>
               {(progress.total / progress.elapsed).toFixed(1)} games/s
             </Text>
@@ -307,6 +307,7 @@ export default function DatabasesPage() {
                             null,
                             "",
                             null,
+                            null,
                           );
                           mutate();
                           setConvertLoading(false);
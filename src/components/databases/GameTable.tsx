@@ -256,6 +256,7 @@ function GameTable({ database }: { database: DatabaseInfo }) {
             columns={[
               {
                 accessor: "white",
+                sortable: true,
                 render: ({ white, white_elo }) => (
                   <div>
                     <Text size="sm" fw={500}>
@@ -269,6 +270,7 @@ function GameTable({ database }: { database: DatabaseInfo }) {
               },
               {
                 accessor: "black",
+                sortable: true,
                 render: ({ black, black_elo }) => (
                   <div>
                     <Text size="sm" fw={500}>
@@ -283,6 +285,7 @@ function GameTable({ database }: { database: DatabaseInfo }) {
               { accessor: "date", sortable: true },
               {
                 accessor: "result",
+                sortable: true,
                 render: ({ result }) => result?.replaceAll("1/2", "½"),
               },
               { accessor: "ply_count", title: "Plies", sortable: true },
@@ -48,9 +48,10 @@ export function PlayerSearchInput({
       page: 1,
       pageSize: 5,
       name: val,
+      normalized: true,
       skip_count: true,
-      sort: "elo",
-      direction: "asc",
+      sort: "game_count",
+      direction: "desc",
     });
     setData(res.data);
   }
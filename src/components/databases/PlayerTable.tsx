@@ -39,6 +39,7 @@ function PlayerTable({ database }: { database: DatabaseInfo }) {
     setLoading(true);
     query_players(file, {
       name: name,
+      normalized: true,
       range: range,
       page: 1,
       pageSize: limit,
@@ -56,6 +57,7 @@ function PlayerTable({ database }: { database: DatabaseInfo }) {
     setSelectedPlayer(null);
     query_players(file, {
       name: name === "" ? undefined : name,
+      normalized: true,
       range: range,
       page: activePage,
       pageSize: limit,
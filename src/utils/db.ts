@@ -1,4 +1,4 @@
-import type { MonthData, Results } from "@/bindings";
+import type { ImportOptions, MonthData, Results } from "@/bindings";
 import type { LocalOptions } from "@/components/panels/database/DatabasePanel";
 import { BaseDirectory, readDir } from "@tauri-apps/api/fs";
 import { fetch } from "@tauri-apps/api/http";
@@ -16,11 +16,21 @@ export interface CompleteGame {
 export interface DatabaseInfo {
   title?: string;
   description?: string;
+  version?: string | null;
+  created_at?: string | null;
+  updated_at?: string | null;
   filename: string;
   game_count?: number;
   player_count?: number;
   event_count?: number;
   storage_size?: number;
+  games_size?: number | null;
+  players_size?: number | null;
+  index_size?: number | null;
+  average_plies?: number | null;
+  white_win_pct?: number | null;
+  black_win_pct?: number | null;
+  draw_pct?: number | null;
   downloadLink?: string;
   error?: string;
   file: string;
@@ -38,6 +48,7 @@ interface Query {
 interface QueryResponse<T> {
   data: T;
   count: number;
+  has_more: boolean;
 }
 
 export type Speed =
@@ -49,17 +60,40 @@ export type Speed =
   | "Correspondence"
   | "Unknown";
 
+export type GameSource = "Lichess" | "ChessCom" | "OTB" | "Other";
+
 export type Outcome = "*" | "1-0" | "0-1" | "1/2-1/2";
 
+export type PlayerResult = "Win" | "Loss" | "Draw";
+
 export interface GameQuery extends Query {
   player1?: number;
   player2?: number;
   tournament_id?: number;
+  round?: string;
   sides?: Sides;
   rangePlayer1?: [number, number];
   rangePlayer2?: [number, number];
+  min_rating?: number;
+  max_rating?: number;
+  rating_match?: "Both" | "Either";
   speed?: Speed;
   outcome?: Outcome;
+  player_result?: { player: number; result: PlayerResult };
+  eco?: string;
+  min_plies?: number;
+  max_plies?: number;
+  standard_start?: boolean;
+  fen?: string;
+  variant?: string;
+  annotator?: string;
+  source?: GameSource;
+  tag?: string;
+  final_material?: string;
+  is_puzzle?: boolean;
+  starred?: boolean;
+  has_annotations?: boolean;
+  include_deleted?: boolean;
   start_date?: string;
   end_date?: string;
 }
@@ -90,36 +124,178 @@ function normalizeRange(
   return range;
 }
 
+function gameQueryArgs(query: GameQuery) {
+  return {
+    options: {
+      skip_count: query.skip_count ?? false,
+      page: query.page,
+      page_size: query.pageSize,
+      sort: query.sort,
+      direction: query.direction,
+    },
+    player1: query.player1,
+    range1: normalizeRange(query.rangePlayer1),
+    player2: query.player2,
+    range2: normalizeRange(query.rangePlayer2),
+    min_rating: query.min_rating,
+    max_rating: query.max_rating,
+    rating_match: query.rating_match,
+    tournament_id: query.tournament_id,
+    round: query.round,
+    sides: query.sides,
+    speed: query.speed,
+    outcome: query.outcome,
+    player_result: query.player_result,
+    eco: query.eco,
+    min_plies: query.min_plies,
+    max_plies: query.max_plies,
+    standard_start: query.standard_start,
+    fen: query.fen,
+    variant: query.variant,
+    annotator: query.annotator,
+    source: query.source,
+    tag: query.tag,
+    final_material: query.final_material,
+    is_puzzle: query.is_puzzle,
+    has_annotations: query.has_annotations,
+    include_deleted: query.include_deleted,
+    starred: query.starred,
+    start_date: query.start_date,
+    end_date: query.end_date,
+  };
+}
+
 export async function query_games(
   db: string,
   query: GameQuery,
 ): Promise<QueryResponse<NormalizedGame[]>> {
-  return invoke("get_games", {
+  return invoke("get_games", { file: db, query: gameQueryArgs(query) });
+}
+
+export async function countGames(
+  db: string,
+  query: GameQuery,
+): Promise<number> {
+  return invoke("count_games", { file: db, query: gameQueryArgs(query) });
+}
+
+export async function getGame(
+  db: string,
+  id: number,
+): Promise<NormalizedGame | null> {
+  return invoke("get_game", { file: db, id });
+}
+
+export async function getRecentGames(
+  db: string,
+  limit: number,
+): Promise<NormalizedGame[]> {
+  return invoke("get_recent_games", { file: db, limit });
+}
+
+export async function findGamesByFinalPosition(
+  db: string,
+  fen: string,
+  query?: Query,
+): Promise<QueryResponse<NormalizedGame[]>> {
+  return invoke("find_games_by_final_position", {
     file: db,
-    query: {
-      options: {
-        skip_count: query.skip_count ?? false,
-        page: query.page,
-        page_size: query.pageSize,
-        sort: query.sort,
-        direction: query.direction,
-      },
-      player1: query.player1,
-      range1: normalizeRange(query.rangePlayer1),
-      player2: query.player2,
-      range2: normalizeRange(query.rangePlayer2),
-      tournament_id: query.tournament_id,
-      sides: query.sides,
-      speed: query.speed,
-      outcome: query.outcome,
-      start_date: query.start_date,
-      end_date: query.end_date,
+    fen,
+    options: query && {
+      skip_count: query.skip_count ?? false,
+      page: query.page,
+      page_size: query.pageSize,
+      sort: query.sort,
+      direction: query.direction,
     },
   });
 }
 
+export async function addTag(
+  db: string,
+  gameId: number,
+  tag: string,
+): Promise<void> {
+  return invoke("add_tag", { file: db, gameId, tag });
+}
+
+export async function removeTag(
+  db: string,
+  gameId: number,
+  tag: string,
+): Promise<boolean> {
+  return invoke("remove_tag", { file: db, gameId, tag });
+}
+
+export async function toggleStar(
+  db: string,
+  id: number,
+): Promise<boolean | null> {
+  return invoke("toggle_star", { file: db, id });
+}
+
+export async function getTags(db: string, gameId: number): Promise<string[]> {
+  return invoke("get_tags", { file: db, gameId });
+}
+
+export interface ImportEstimate {
+  sampled_games: number;
+  estimated_games: number;
+  estimated_seconds: number;
+  exact: boolean;
+}
+
+export async function estimateImport(
+  file: string,
+  options?: ImportOptions,
+): Promise<ImportEstimate> {
+  return invoke("estimate_import", { file, options });
+}
+
+export interface MoveTreeEntry {
+  san: string;
+  games: number;
+  white_wins: number;
+  black_wins: number;
+  draws: number;
+}
+
+export async function getMoveTree(
+  db: string,
+  moves: string[],
+  limit?: number,
+): Promise<MoveTreeEntry[]> {
+  return invoke("get_move_tree", { file: db, moves, limit });
+}
+
+export async function getGameMoves(
+  db: string,
+  id: number,
+): Promise<string[] | null> {
+  return invoke("get_game_moves", { file: db, id });
+}
+
+export async function getPlayerGames(
+  db: string,
+  id: number,
+  limit: number,
+  offset: number,
+  sort?: string,
+  direction?: "asc" | "desc",
+): Promise<QueryResponse<NormalizedGame[]>> {
+  return invoke("get_player_games", {
+    file: db,
+    id,
+    limit,
+    offset,
+    sort,
+    direction,
+  });
+}
+
 interface PlayerQuery extends Query {
   name?: string;
+  normalized?: boolean;
   range?: [number, number];
 }
 
@@ -145,6 +321,7 @@ export async function query_players(
         direction: query.direction,
       },
       name: query.name,
+      normalized: query.normalized,
       range: normalizeRange(query.range),
     },
   });
@@ -270,6 +447,12 @@ export type NormalizedGame = {
   result: Outcome;
   time_control?: string;
   eco?: string;
+  opening?: string;
+  move_text?: string;
+  variant?: string | null;
+  annotator?: string | null;
+  source?: GameSource | null;
+  starred?: boolean;
   ply_count: number;
   white_material?: number;
   black_material?: number;
@@ -277,23 +460,42 @@ export type NormalizedGame = {
 };
 
 export async function getTournamentGames(file: string, id: number) {
-  return await query_games(file, {
-    direction: "asc",
-    sort: "id",
-    tournament_id: id,
-    skip_count: true,
-  });
+  const pageSize = 1000;
+  const games: NormalizedGame[] = [];
+  for (let page = 1; ; page++) {
+    const res = await query_games(file, {
+      direction: "asc",
+      sort: "id",
+      tournament_id: id,
+      skip_count: true,
+      page,
+      pageSize,
+    });
+    games.push(...res.data);
+    if (!res.has_more) {
+      return { data: games };
+    }
+  }
 }
 
 export interface PlayerGameInfo {
   won: number;
   lost: number;
   draw: number;
+  white_results: Results;
+  black_results: Results;
   data_per_month: [string, MonthData][];
   white_openings: [string, Results][];
   black_openings: [string, Results][];
 }
 
+export async function getPlayersGameInfoBulk(
+  db: string,
+  ids: number[],
+): Promise<Record<number, PlayerGameInfo>> {
+  return invoke("get_players_game_info_bulk", { file: db, ids });
+}
+
 export async function searchPosition(options: LocalOptions, tab: string) {
   const openings: [Opening[], NormalizedGame[]] = await invoke(
     "search_position",